@@ -20,7 +20,10 @@
 //!
 //! - `new`: Creates a new bonding curve instance
 //! - `get_buy_price`: Calculates the amount of tokens received for a given SOL amount
+//! - `get_buy_price_with_fee`: Calculates the amount of tokens received for a given SOL amount, including fees
+//! - `get_buy_sol_cost`: Calculates the SOL cost, including fees, to buy an exact token amount
 //! - `get_sell_price`: Calculates the amount of SOL received for selling tokens
+//! - `get_sell_token_amount`: Calculates the tokens to sell to receive a target amount of SOL after fees
 //! - `get_market_cap_sol`: Calculates the current market cap in SOL
 //! - `get_final_market_cap_sol`: Calculates the final market cap in SOL after all tokens are sold
 //! - `get_buy_out_price`: Calculates the price to buy out all remaining tokens
@@ -115,6 +118,77 @@ impl BondingCurveAccount {
         })
     }
 
+    /// Calculates the amount of tokens received for a given SOL amount, including fees
+    ///
+    /// Unlike `get_buy_price`, the protocol fee is paid out of `amount`: the returned tokens are
+    /// the most whose cost from `get_buy_sol_cost` stays within `amount`.
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of SOL to spend, including fees
+    /// * `fee_basis_points` - Fee in basis points (1/100th of a percent)
+    ///
+    /// # Returns
+    /// * `Ok(u64)` - Amount of tokens that would be received
+    /// * `Err(&str)` - Error message if curve is complete
+    pub fn get_buy_price_with_fee(&self, amount: u64, fee_basis_points: u64) -> Result<u64, &'static str> {
+        if self.complete {
+            return Err("Curve is complete");
+        }
+
+        // Calculate the largest curve cost whose fee, rounded down like the program, fits in the amount
+        let with_fee = |cost: u128| cost + (cost * (fee_basis_points as u128)) / 10000;
+        let mut sol_cost: u128 = ((amount as u128) * 10000) / (10000 + (fee_basis_points as u128));
+        while with_fee(sol_cost + 1) <= amount as u128 {
+            sol_cost += 1;
+        }
+
+        if sol_cost == 0 {
+            return Ok(0);
+        }
+
+        // Solve amount * vsol / (vtoken - amount) + 1 <= sol_cost for the largest amount
+        let s: u128 = ((self.virtual_token_reserves as u128) * sol_cost).saturating_sub(1)
+            / ((self.virtual_sol_reserves as u128) + sol_cost);
+
+        Ok((s as u64).min(self.real_token_reserves))
+    }
+
+    /// Calculates the SOL cost to buy an exact amount of tokens
+    ///
+    /// This is the inverse of `get_buy_price` and matches how the program charges a buy:
+    /// the curve cost of the requested tokens plus the protocol fee on top of it.
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of tokens to buy
+    /// * `fee_basis_points` - Fee in basis points (1/100th of a percent)
+    ///
+    /// # Returns
+    /// * `Ok(u64)` - Amount of SOL required, including fees
+    /// * `Err(&str)` - Error message if curve is complete or has too few tokens left
+    pub fn get_buy_sol_cost(&self, amount: u64, fee_basis_points: u64) -> Result<u64, &'static str> {
+        if self.complete {
+            return Err("Curve is complete");
+        }
+
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        if amount > self.real_token_reserves || amount >= self.virtual_token_reserves {
+            return Err("Not enough tokens in the bonding curve");
+        }
+
+        // Calculate the SOL the curve takes for the tokens, rounding up like the program
+        let n: u128 = (amount as u128) * (self.virtual_sol_reserves as u128);
+        let d: u128 = (self.virtual_token_reserves as u128) - (amount as u128);
+        let sol_cost: u128 = n / d + 1;
+
+        // Calculate the fee charged on top of the curve cost
+        let fee: u128 = (sol_cost * (fee_basis_points as u128)) / 10000;
+
+        u64::try_from(sol_cost + fee).map_err(|_| "SOL cost overflows u64")
+    }
+
     /// Calculates the amount of SOL received for selling tokens
     ///
     /// # Arguments
@@ -144,6 +218,45 @@ impl BondingCurveAccount {
         Ok((n - a) as u64)
     }
 
+    /// Calculates the amount of tokens to sell to receive a target amount of SOL
+    ///
+    /// This is the inverse of `get_sell_price`: selling the returned amount yields at least
+    /// `sol_amount` after the protocol fee is deducted.
+    ///
+    /// # Arguments
+    /// * `sol_amount` - Amount of SOL to receive, net of fees
+    /// * `fee_basis_points` - Fee in basis points (1/100th of a percent)
+    ///
+    /// # Returns
+    /// * `Ok(u64)` - Amount of tokens to sell
+    /// * `Err(&str)` - Error message if curve is complete or cannot pay out the target
+    pub fn get_sell_token_amount(&self, sol_amount: u64, fee_basis_points: u64) -> Result<u64, &'static str> {
+        if self.complete {
+            return Err("Curve is complete");
+        }
+
+        if sol_amount == 0 {
+            return Ok(0);
+        }
+
+        if fee_basis_points >= 10000 {
+            return Err("Fee must be below 10000 basis points");
+        }
+
+        // Calculate the smallest payout before fees whose net, with the fee rounded down, reaches the target
+        let gross: u128 = ((sol_amount as u128 - 1) * 10000) / (10000 - (fee_basis_points as u128)) + 1;
+
+        if gross > (self.real_sol_reserves as u128) || gross >= (self.virtual_sol_reserves as u128) {
+            return Err("Not enough SOL in the bonding curve");
+        }
+
+        // Solve amount * vsol / (vtoken + amount) >= gross for the smallest amount
+        let amount: u128 = (gross * (self.virtual_token_reserves as u128))
+            .div_ceil((self.virtual_sol_reserves as u128) - gross);
+
+        u64::try_from(amount).map_err(|_| "Token amount overflows u64")
+    }
+
     /// Calculates the current market cap in SOL
     pub fn get_market_cap_sol(&self) -> u64 {
         if self.virtual_token_reserves == 0 {
@@ -251,20 +364,116 @@ mod tests {
         assert!(sell_price > 0);
     }
 
+    #[test]
+    fn test_buy_sol_cost() {
+        let bonding_curve: BondingCurveAccount = get_bonding_curve();
+
+        assert_eq!(bonding_curve.get_buy_sol_cost(0, 250).unwrap(), 0);
+
+        // 100 tokens cost 100 * 1000 / 900 + 1 = 112 lamports, plus a 2.5% fee
+        assert_eq!(bonding_curve.get_buy_sol_cost(100, 0).unwrap(), 112);
+        assert_eq!(bonding_curve.get_buy_sol_cost(100, 250).unwrap(), 114);
+
+        // Spending the fee-less cost must buy at least the requested tokens
+        let cost = bonding_curve.get_buy_sol_cost(100, 0).unwrap();
+        assert!(bonding_curve.get_buy_price(cost).unwrap() >= 100);
+
+        // Cannot buy more than the real token reserves
+        assert!(bonding_curve.get_buy_sol_cost(501, 250).is_err());
+    }
+
+    #[test]
+    fn test_buy_price_with_fee() {
+        let bonding_curve: BondingCurveAccount = get_bonding_curve();
+
+        assert_eq!(bonding_curve.get_buy_price_with_fee(0, 250).unwrap(), 0);
+        assert_eq!(bonding_curve.get_buy_price_with_fee(1, 250).unwrap(), 0);
+
+        // 114 lamports buy the 100 tokens that cost 114 with the 2.5% fee, 113 do not
+        assert_eq!(bonding_curve.get_buy_price_with_fee(114, 250).unwrap(), 100);
+        assert_eq!(bonding_curve.get_buy_price_with_fee(113, 250).unwrap(), 99);
+
+        // Capped by the real token reserves
+        assert_eq!(bonding_curve.get_buy_price_with_fee(u64::MAX, 250).unwrap(), bonding_curve.real_token_reserves);
+    }
+
+    #[test]
+    fn test_buy_price_with_fee_is_the_most_affordable() {
+        let curves = [
+            get_bonding_curve(),
+            BondingCurveAccount::new(1, 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 0, 1_000_000_000_000_000, false),
+            BondingCurveAccount::new(1, 412_345_678_901_234, 78_123_456_789, 132_445_678_901_234, 48_123_456_789, 1_000_000_000_000_000, false),
+        ];
+
+        // A fixed xorshift sequence, so failures reproduce
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for bonding_curve in &curves {
+            for fee_basis_points in [0, 1, 100, 250, 9999] {
+                for _ in 0..200 {
+                    // Spread the amounts over every magnitude up to twice the virtual SOL reserves
+                    let amount = next() % (((bonding_curve.virtual_sol_reserves * 2) >> (next() % 40)) + 1);
+                    let tokens = bonding_curve.get_buy_price_with_fee(amount, fee_basis_points).unwrap();
+
+                    // The tokens never cost more than the amount, fee included
+                    assert!(bonding_curve.get_buy_sol_cost(tokens, fee_basis_points).unwrap() <= amount);
+
+                    // One more token costs more than the amount, unless none are left
+                    if tokens < bonding_curve.real_token_reserves {
+                        assert!(bonding_curve.get_buy_sol_cost(tokens + 1, fee_basis_points).unwrap() > amount);
+                    }
+
+                    // The fee only ever lowers the tokens received
+                    assert!(tokens <= bonding_curve.get_buy_price_with_fee(amount, 0).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sell_token_amount() {
+        let bonding_curve: BondingCurveAccount = get_bonding_curve();
+
+        assert_eq!(bonding_curve.get_sell_token_amount(0, 250).unwrap(), 0);
+
+        // Selling the solved amount must yield at least the target, one token less must not
+        for target in [1, 10, 50, 100, 200] {
+            let amount = bonding_curve.get_sell_token_amount(target, 250).unwrap();
+            assert!(bonding_curve.get_sell_price(amount, 250).unwrap() >= target);
+            assert!(bonding_curve.get_sell_price(amount - 1, 250).unwrap() < target);
+        }
+
+        // Cannot take out more SOL than the curve holds
+        assert!(bonding_curve.get_sell_token_amount(501, 250).is_err());
+        assert!(bonding_curve.get_sell_token_amount(100, 10000).is_err());
+    }
+
     #[test]
     fn test_bonding_curve_complete() {
         let mut bonding_curve: BondingCurveAccount = get_bonding_curve();
 
         // Test operations work when not complete
         assert!(bonding_curve.get_buy_price(100).is_ok());
+        assert!(bonding_curve.get_buy_price_with_fee(100, 250).is_ok());
         assert!(bonding_curve.get_sell_price(100, 250).is_ok());
+        assert!(bonding_curve.get_buy_sol_cost(100, 250).is_ok());
+        assert!(bonding_curve.get_sell_token_amount(50, 250).is_ok());
 
         // Set curve to complete
         bonding_curve.complete = true;
 
         // Test operations fail when complete
         assert!(bonding_curve.get_buy_price(100).is_err());
+        assert!(bonding_curve.get_buy_price_with_fee(100, 250).is_err());
         assert!(bonding_curve.get_sell_price(100, 250).is_err());
+        assert!(bonding_curve.get_buy_sol_cost(100, 250).is_err());
+        assert!(bonding_curve.get_sell_token_amount(50, 250).is_err());
     }
 
     #[test]
@@ -299,6 +508,10 @@ mod tests {
         let buy_price = bonding_curve.get_buy_price(u64::MAX).unwrap();
         assert!(buy_price > 0);
         assert!(buy_price <= bonding_curve.real_token_reserves);
+
+        let buy_price = bonding_curve.get_buy_price_with_fee(u64::MAX, 250).unwrap();
+        assert!(buy_price > 0);
+        assert!(buy_price <= bonding_curve.real_token_reserves);
     }
 
     #[test]
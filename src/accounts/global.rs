@@ -22,10 +22,14 @@
 //!
 //! - `new`: Creates a new global account instance
 //! - `get_initial_buy_price`: Calculates the initial amount of tokens received for a given SOL amount
+//! - `get_initial_buy_price_with_fee`: Calculates the initial amount of tokens received for a given SOL amount, including fees
+//! - `initial_bonding_curve`: Returns the bonding curve a new token starts with
 
 use solana_sdk::pubkey::Pubkey;
 use borsh::{BorshDeserialize, BorshSerialize};
 
+use super::BondingCurveAccount;
+
 /// Represents the global configuration account for token pricing and fees
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct GlobalAccount {
@@ -111,6 +115,35 @@ impl GlobalAccount {
             self.initial_real_token_reserves
         }
     }
+
+    /// Calculates the initial amount of tokens received for a given SOL amount, including fees
+    ///
+    /// The protocol fee of `fee_basis_points` is paid out of `amount`, as in
+    /// `BondingCurveAccount::get_buy_price_with_fee`.
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of SOL to spend, including fees
+    ///
+    /// # Returns
+    /// Amount of tokens that would be received
+    pub fn get_initial_buy_price_with_fee(&self, amount: u64) -> u64 {
+        self.initial_bonding_curve()
+            .get_buy_price_with_fee(amount, self.fee_basis_points)
+            .unwrap_or_default()
+    }
+
+    /// Returns the bonding curve a new token starts with
+    pub fn initial_bonding_curve(&self) -> BondingCurveAccount {
+        BondingCurveAccount::new(
+            0,
+            self.initial_virtual_token_reserves,
+            self.initial_virtual_sol_reserves,
+            self.initial_real_token_reserves,
+            0,
+            self.token_total_supply,
+            false,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -157,6 +190,26 @@ mod tests {
         assert!(price <= global.initial_real_token_reserves);
     }
 
+    #[test]
+    fn test_global_account_initial_buy_price_with_fee() {
+        let mut global: GlobalAccount = get_global();
+
+        assert_eq!(global.get_initial_buy_price_with_fee(0), 0);
+
+        // The fee comes out of the amount, so it buys fewer tokens than the fee-less price
+        let price: u64 = global.get_initial_buy_price_with_fee(100);
+        assert!(price > 0);
+        assert!(price < global.get_initial_buy_price(100));
+
+        let bonding_curve = global.initial_bonding_curve();
+        assert_eq!(price, bonding_curve.get_buy_price_with_fee(100, global.fee_basis_points).unwrap());
+        assert!(bonding_curve.get_buy_sol_cost(price, global.fee_basis_points).unwrap() <= 100);
+
+        // Capped by the initial real token reserves
+        global.initial_real_token_reserves = 100;
+        assert_eq!(global.get_initial_buy_price_with_fee(1000), global.initial_real_token_reserves);
+    }
+
     #[test]
     fn test_global_account_max_reserves() {
         let mut global: GlobalAccount = get_global();
@@ -0,0 +1,439 @@
+//! Pump.fun account cache
+//!
+//! Keeps the Global account and the bonding curves of interest in memory, updated by websocket
+//! `accountSubscribe` streams or by the caller, for example from a Yellowstone account feed. Trade
+//! builders read accounts from the cache and fetch them over RPC when they are not cached.
+//!
+//! Every cached account carries the slot it was observed at, so callers can judge its staleness.
+//! Accounts fetched over RPC are cached as well, and an account that lags the current slot by more
+//! than the cache's maximum slot lag is fetched again. The current slot is estimated from the newest
+//! slot the cache has seen and the time since.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, Weak},
+};
+
+use borsh::BorshDeserialize;
+use futures::StreamExt;
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient}, rpc_config::RpcAccountInfoConfig,
+};
+use solana_sdk::{
+    clock::{Slot, DEFAULT_MS_PER_SLOT}, commitment_config::CommitmentConfig, pubkey::Pubkey,
+};
+use tokio::{task::JoinHandle, time::Instant};
+
+use crate::{
+    accounts::{BondingCurveAccount, GlobalAccount},
+    constants::trade::{ACCOUNT_MAX_SLOT_LAG, ACCOUNT_RESUBSCRIBE_DELAY},
+    error::{ClientError, ClientResult},
+    trade::common::{get_bonding_curve_pda, get_global_pda},
+};
+
+/// An account and the slot it was observed at
+#[derive(Debug, Clone)]
+pub struct CachedAccount<T> {
+    pub account: Arc<T>,
+    pub slot: Slot,
+}
+
+impl<T> CachedAccount<T> {
+    /// Returns how many slots the account is behind `current_slot`
+    pub fn slot_lag(&self, current_slot: Slot) -> u64 {
+        current_slot.saturating_sub(self.slot)
+    }
+}
+
+/// Which cached account a subscription updates
+#[derive(Debug, Clone, Copy)]
+enum CacheEntry {
+    Global,
+    BondingCurve(Pubkey),
+}
+
+/// In-memory cache of the Global account and bonding curves
+///
+/// Clones share the cache and its subscriptions. The subscriptions stop when the last clone is
+/// dropped.
+#[derive(Clone)]
+pub struct AccountCache {
+    inner: Arc<Inner>,
+    max_slot_lag: u64,
+}
+
+struct Inner {
+    commitment: CommitmentConfig,
+    /// Newest slot seen and when it was seen
+    latest_slot: RwLock<Option<(Slot, Instant)>>,
+    global_account: RwLock<Option<CachedAccount<GlobalAccount>>>,
+    /// Bonding curves by mint
+    bonding_curves: RwLock<HashMap<Pubkey, CachedAccount<BondingCurveAccount>>>,
+    /// Subscription tasks by subscribed account
+    subscriptions: RwLock<HashMap<Pubkey, JoinHandle<()>>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Ok(subscriptions) = self.subscriptions.get_mut() {
+            for task in subscriptions.values() {
+                task.abort();
+            }
+        }
+    }
+}
+
+impl Inner {
+    fn update(&self, entry: CacheEntry, slot: Slot, data: &[u8]) -> ClientResult<()> {
+        self.observe_slot(slot);
+
+        match entry {
+            CacheEntry::Global => {
+                let account = GlobalAccount::try_from_slice(data).map_err(ClientError::BorshError)?;
+                let mut cached = self.global_account.write().unwrap();
+                if cached.as_ref().is_none_or(|cached| cached.slot <= slot) {
+                    *cached = Some(CachedAccount { account: Arc::new(account), slot });
+                }
+            }
+            CacheEntry::BondingCurve(mint) => {
+                let account = BondingCurveAccount::try_from_slice(data).map_err(ClientError::BorshError)?;
+                let mut bonding_curves = self.bonding_curves.write().unwrap();
+                if bonding_curves.get(&mint).is_none_or(|cached| cached.slot <= slot) {
+                    bonding_curves.insert(mint, CachedAccount { account: Arc::new(account), slot });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn observe_slot(&self, slot: Slot) {
+        let mut latest_slot = self.latest_slot.write().unwrap();
+        if latest_slot.is_none_or(|(latest_slot, _)| latest_slot < slot) {
+            *latest_slot = Some((slot, Instant::now()));
+        }
+    }
+
+    /// Estimates the current slot from the newest slot seen and the time since
+    fn current_slot(&self) -> Option<Slot> {
+        self.latest_slot.read().unwrap()
+            .map(|(slot, seen_at)| slot + seen_at.elapsed().as_millis() as u64 / DEFAULT_MS_PER_SLOT)
+    }
+}
+
+impl AccountCache {
+    /// Creates an empty cache, subscriptions use the given commitment
+    pub fn new(commitment: CommitmentConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                commitment,
+                latest_slot: RwLock::new(None),
+                global_account: RwLock::new(None),
+                bonding_curves: RwLock::new(HashMap::new()),
+                subscriptions: RwLock::new(HashMap::new()),
+            }),
+            max_slot_lag: ACCOUNT_MAX_SLOT_LAG,
+        }
+    }
+
+    /// Sets how many slots a cached account may lag the current slot before it is fetched again
+    pub fn with_max_slot_lag(mut self, max_slot_lag: u64) -> Self {
+        self.max_slot_lag = max_slot_lag;
+        self
+    }
+
+    /// Returns the cached Global account, or fetches and caches it over RPC if it is not cached
+    /// or is stale
+    pub async fn get_global_account(&self, rpc: &RpcClient) -> ClientResult<Arc<GlobalAccount>> {
+        if let Some(cached) = self.cached_global_account().filter(|cached| self.is_fresh(cached)) {
+            return Ok(cached.account);
+        }
+
+        let global = get_global_pda();
+        let not_found = || ClientError::Solana("Global account not found".to_string(), global.to_string());
+        if !self.fetch(rpc, &global, CacheEntry::Global).await? {
+            return Err(not_found());
+        }
+        self.cached_global_account()
+            .map(|cached| cached.account)
+            .ok_or_else(not_found)
+    }
+
+    /// Returns the cached bonding curve of `mint`, or fetches and caches it over RPC if it is not
+    /// cached or is stale
+    pub async fn get_bonding_curve_account(&self, rpc: &RpcClient, mint: &Pubkey) -> ClientResult<Arc<BondingCurveAccount>> {
+        let cached = match self.cached_bonding_curve_account(mint).filter(|cached| self.is_fresh(cached)) {
+            Some(cached) => cached,
+            None => {
+                let bonding_curve_pda = get_bonding_curve_pda(mint)
+                    .ok_or(ClientError::BondingCurveNotFound)?;
+                if !self.fetch(rpc, &bonding_curve_pda, CacheEntry::BondingCurve(*mint)).await? {
+                    return Err(ClientError::BondingCurveNotFound);
+                }
+                self.cached_bonding_curve_account(mint)
+                    .ok_or(ClientError::BondingCurveNotFound)?
+            }
+        };
+
+        if cached.account.complete {
+            return Err(ClientError::BondingCurveComplete);
+        }
+
+        Ok(cached.account)
+    }
+
+    /// Fetches the account at `address` over RPC and caches it with the slot it was read at,
+    /// returns false if the account does not exist
+    async fn fetch(&self, rpc: &RpcClient, address: &Pubkey, entry: CacheEntry) -> ClientResult<bool> {
+        let response = rpc.get_account_with_commitment(address, rpc.commitment()).await?;
+        match response.value {
+            Some(account) => {
+                self.inner.update(entry, response.context.slot, &account.data)?;
+                Ok(true)
+            }
+            None => {
+                self.inner.observe_slot(response.context.slot);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Returns true if the account lags the current slot by at most the maximum slot lag
+    fn is_fresh<T>(&self, cached: &CachedAccount<T>) -> bool {
+        self.inner.current_slot()
+            .is_some_and(|current_slot| cached.slot_lag(current_slot) <= self.max_slot_lag)
+    }
+
+    /// Records a slot the chain has reached, for example from a slot subscription, so cached
+    /// accounts are judged against it
+    pub fn update_slot(&self, slot: Slot) {
+        self.inner.observe_slot(slot);
+    }
+
+    /// Returns the cached Global account without fetching
+    pub fn cached_global_account(&self) -> Option<CachedAccount<GlobalAccount>> {
+        self.inner.global_account.read().unwrap().clone()
+    }
+
+    /// Returns the cached bonding curve of `mint` without fetching
+    pub fn cached_bonding_curve_account(&self, mint: &Pubkey) -> Option<CachedAccount<BondingCurveAccount>> {
+        self.inner.bonding_curves.read().unwrap().get(mint).cloned()
+    }
+
+    /// Stores the Global account data observed at `slot`, unless a newer one is cached
+    pub fn update_global_account(&self, slot: Slot, data: &[u8]) -> ClientResult<()> {
+        self.inner.update(CacheEntry::Global, slot, data)
+    }
+
+    /// Stores the bonding curve data of `mint` observed at `slot`, unless a newer one is cached
+    pub fn update_bonding_curve_account(&self, mint: &Pubkey, slot: Slot, data: &[u8]) -> ClientResult<()> {
+        self.inner.update(CacheEntry::BondingCurve(*mint), slot, data)
+    }
+
+    /// Keeps the Global account updated from a websocket subscription
+    pub fn subscribe_global_account(&self, ws_url: &str) {
+        self.subscribe(ws_url, get_global_pda(), CacheEntry::Global);
+    }
+
+    /// Keeps the bonding curve of `mint` updated from a websocket subscription
+    pub fn subscribe_bonding_curve(&self, ws_url: &str, mint: &Pubkey) -> ClientResult<()> {
+        let bonding_curve_pda = get_bonding_curve_pda(mint)
+            .ok_or(ClientError::BondingCurveNotFound)?;
+        self.subscribe(ws_url, bonding_curve_pda, CacheEntry::BondingCurve(*mint));
+        Ok(())
+    }
+
+    /// Stops updating the bonding curve of `mint` and removes it from the cache
+    pub fn unsubscribe_bonding_curve(&self, mint: &Pubkey) {
+        if let Some(bonding_curve_pda) = get_bonding_curve_pda(mint) {
+            if let Some(task) = self.inner.subscriptions.write().unwrap().remove(&bonding_curve_pda) {
+                task.abort();
+            }
+        }
+        self.inner.bonding_curves.write().unwrap().remove(mint);
+    }
+
+    fn subscribe(&self, ws_url: &str, address: Pubkey, entry: CacheEntry) {
+        let mut subscriptions = self.inner.subscriptions.write().unwrap();
+        if subscriptions.contains_key(&address) {
+            return;
+        }
+
+        let task = tokio::spawn(subscription_loop(
+            Arc::downgrade(&self.inner),
+            ws_url.to_string(),
+            self.inner.commitment,
+            address,
+            entry,
+        ));
+        subscriptions.insert(address, task);
+    }
+}
+
+/// Applies account notifications to the cache, and resubscribes when the stream ends
+async fn subscription_loop(
+    inner: Weak<Inner>,
+    ws_url: String,
+    commitment: CommitmentConfig,
+    address: Pubkey,
+    entry: CacheEntry,
+) {
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(commitment),
+        ..RpcAccountInfoConfig::default()
+    };
+
+    loop {
+        if let Ok(client) = PubsubClient::new(&ws_url).await {
+            if let Ok((mut stream, _unsubscribe)) = client.account_subscribe(&address, Some(config.clone())).await {
+                while let Some(response) = stream.next().await {
+                    let Some(inner) = inner.upgrade() else {
+                        return;
+                    };
+                    if let Some(data) = response.value.data.decode() {
+                        // Data that does not parse is skipped, the next notification replaces it
+                        let _ = inner.update(entry, response.context.slot, &data);
+                    }
+                }
+            }
+        }
+
+        if inner.strong_count() == 0 {
+            return;
+        }
+        tokio::time::sleep(ACCOUNT_RESUBSCRIBE_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use base64::{engine::general_purpose, Engine as _};
+    use borsh::to_vec;
+    use solana_account_decoder_client_types::{UiAccount, UiAccountData};
+    use solana_client::{
+        rpc_request::RpcRequest,
+        rpc_response::{Response, RpcResponseContext},
+    };
+
+    use super::*;
+
+    fn bonding_curve_data(virtual_sol_reserves: u64, complete: bool) -> Vec<u8> {
+        to_vec(&BondingCurveAccount::new(1, 1_000_000, virtual_sol_reserves, 500_000, 0, 1_000_000, complete)).unwrap()
+    }
+
+    /// `getAccountInfo` response with `data` read at `slot`
+    fn account_info(slot: Slot, data: &[u8]) -> serde_json::Value {
+        serde_json::to_value(Response {
+            context: RpcResponseContext { slot, api_version: None },
+            value: Some(UiAccount {
+                lamports: 1_000_000,
+                data: UiAccountData::Binary(general_purpose::STANDARD.encode(data), UiAccountEncoding::Base64),
+                owner: Pubkey::new_unique().to_string(),
+                executable: false,
+                rent_epoch: 0,
+                space: Some(data.len() as u64),
+            }),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_account_cache_updates() {
+        let cache = AccountCache::new(CommitmentConfig::confirmed());
+        let mint = Pubkey::new_unique();
+        assert!(cache.cached_global_account().is_none());
+        assert!(cache.cached_bonding_curve_account(&mint).is_none());
+
+        cache.update_bonding_curve_account(&mint, 100, &bonding_curve_data(1_000, false)).unwrap();
+        let cached = cache.cached_bonding_curve_account(&mint).unwrap();
+        assert_eq!(cached.slot, 100);
+        assert_eq!(cached.account.virtual_sol_reserves, 1_000);
+        assert_eq!(cached.slot_lag(103), 3);
+        assert_eq!(cached.slot_lag(99), 0);
+
+        // Notifications from an older slot do not replace newer data
+        cache.update_bonding_curve_account(&mint, 99, &bonding_curve_data(2_000, false)).unwrap();
+        assert_eq!(cache.cached_bonding_curve_account(&mint).unwrap().account.virtual_sol_reserves, 1_000);
+
+        cache.update_bonding_curve_account(&mint, 101, &bonding_curve_data(3_000, false)).unwrap();
+        let cached = cache.cached_bonding_curve_account(&mint).unwrap();
+        assert_eq!((cached.slot, cached.account.virtual_sol_reserves), (101, 3_000));
+
+        assert!(cache.update_bonding_curve_account(&mint, 102, &[1, 2, 3]).is_err());
+        assert_eq!(cache.cached_bonding_curve_account(&mint).unwrap().slot, 101);
+
+        cache.unsubscribe_bonding_curve(&mint);
+        assert!(cache.cached_bonding_curve_account(&mint).is_none());
+
+        let global_account = GlobalAccount::new(1, true, Pubkey::new_unique(), Pubkey::new_unique(), 1, 2, 3, 4, 100);
+        cache.update_global_account(50, &to_vec(&global_account).unwrap()).unwrap();
+        let cached = cache.cached_global_account().unwrap();
+        assert_eq!(cached.slot, 50);
+        assert_eq!(cached.account.fee_recipient, global_account.fee_recipient);
+    }
+
+    #[tokio::test]
+    async fn test_account_cache_complete_bonding_curve() {
+        let cache = AccountCache::new(CommitmentConfig::confirmed());
+        let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+        let mint = Pubkey::new_unique();
+
+        cache.update_bonding_curve_account(&mint, 100, &bonding_curve_data(1_000, true)).unwrap();
+        assert!(matches!(
+            cache.get_bonding_curve_account(&rpc, &mint).await,
+            Err(ClientError::BondingCurveComplete)
+        ));
+
+        cache.update_bonding_curve_account(&mint, 101, &bonding_curve_data(1_000, false)).unwrap();
+        assert_eq!(cache.get_bonding_curve_account(&rpc, &mint).await.unwrap().virtual_sol_reserves, 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_account_cache_caches_fetched_accounts() {
+        let cache = AccountCache::new(CommitmentConfig::confirmed());
+        let mint = Pubkey::new_unique();
+        let mocks = HashMap::from([(RpcRequest::GetAccountInfo, account_info(200, &bonding_curve_data(2_000, false)))]);
+        let rpc = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+
+        assert_eq!(cache.get_bonding_curve_account(&rpc, &mint).await.unwrap().virtual_sol_reserves, 2_000);
+        let cached = cache.cached_bonding_curve_account(&mint).unwrap();
+        assert_eq!((cached.slot, cached.account.virtual_sol_reserves), (200, 2_000));
+
+        // The mock answers once, later reads are served from the cache
+        assert_eq!(cache.get_bonding_curve_account(&rpc, &mint).await.unwrap().virtual_sol_reserves, 2_000);
+    }
+
+    #[tokio::test]
+    async fn test_account_cache_refetches_stale_accounts() {
+        tokio::time::pause();
+        let cache = AccountCache::new(CommitmentConfig::confirmed()).with_max_slot_lag(10);
+        let mint = Pubkey::new_unique();
+        // The mock reports no account, so a refetch shows up as `BondingCurveNotFound`
+        let rpc = RpcClient::new_mock("succeeds".to_string());
+
+        cache.update_bonding_curve_account(&mint, 100, &bonding_curve_data(1_000, false)).unwrap();
+        cache.update_slot(110);
+        assert_eq!(cache.get_bonding_curve_account(&rpc, &mint).await.unwrap().virtual_sol_reserves, 1_000);
+
+        cache.update_slot(111);
+        assert!(matches!(
+            cache.get_bonding_curve_account(&rpc, &mint).await,
+            Err(ClientError::BondingCurveNotFound)
+        ));
+
+        // Without newer slots the current slot is estimated from the time passed
+        cache.update_bonding_curve_account(&mint, 120, &bonding_curve_data(3_000, false)).unwrap();
+        tokio::time::advance(Duration::from_millis(DEFAULT_MS_PER_SLOT * 10)).await;
+        assert_eq!(cache.get_bonding_curve_account(&rpc, &mint).await.unwrap().virtual_sol_reserves, 3_000);
+
+        tokio::time::advance(Duration::from_millis(DEFAULT_MS_PER_SLOT)).await;
+        assert!(matches!(
+            cache.get_bonding_curve_account(&rpc, &mint).await,
+            Err(ClientError::BondingCurveNotFound)
+        ));
+    }
+}
@@ -0,0 +1,266 @@
+//! Recent blockhash cache
+//!
+//! Trade builders take their blockhash from a `BlockhashCache` instead of fetching one per
+//! transaction. The cache refreshes itself in the background from the first time it is used, and
+//! only fetches on the caller's path when the cached blockhash is older than `BLOCKHASH_MAX_AGE`.
+
+use std::sync::{Arc, OnceLock, Weak};
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash};
+use tokio::{sync::RwLock, task::JoinHandle, time::Instant};
+
+use crate::{
+    constants::trade::{BLOCKHASH_MAX_AGE, BLOCKHASH_REFRESH_INTERVAL},
+    error::ClientResult,
+};
+
+/// A recent blockhash and the last block height a transaction using it can land at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatestBlockhash {
+    pub blockhash: Hash,
+    pub last_valid_block_height: u64,
+    /// When the blockhash was fetched
+    pub fetched_at: Instant,
+}
+
+impl LatestBlockhash {
+    /// Returns true if the blockhash is too old to be handed out without a refresh
+    pub fn is_stale(&self) -> bool {
+        self.fetched_at.elapsed() >= BLOCKHASH_MAX_AGE
+    }
+}
+
+/// Recent blockhash shared by all trade builders, refreshed in the background
+///
+/// Clones share the cache and its refresh task. The task stops when the last clone is dropped.
+#[derive(Clone)]
+pub struct BlockhashCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    rpc: RpcClient,
+    latest: RwLock<Option<LatestBlockhash>>,
+    refresh_task: OnceLock<JoinHandle<()>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(task) = self.refresh_task.get() {
+            task.abort();
+        }
+    }
+}
+
+impl Inner {
+    async fn refresh(&self) -> ClientResult<LatestBlockhash> {
+        let (blockhash, last_valid_block_height) = self.rpc
+            .get_latest_blockhash_with_commitment(self.rpc.commitment())
+            .await?;
+
+        let latest = LatestBlockhash {
+            blockhash,
+            last_valid_block_height,
+            fetched_at: Instant::now(),
+        };
+        *self.latest.write().await = Some(latest);
+
+        Ok(latest)
+    }
+}
+
+impl BlockhashCache {
+    /// Creates a cache that fetches confirmed blockhashes from `rpc_url`
+    ///
+    /// A processed blockhash may belong to a fork that never confirms, and a transaction using
+    /// it would never land.
+    pub fn new(rpc_url: String) -> Self {
+        Self::new_with_rpc(RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()))
+    }
+
+    /// Creates a cache that fetches blockhashes from `rpc` at its commitment, which should be
+    /// confirmed for the same reason as in [`BlockhashCache::new`]
+    pub fn new_with_rpc(rpc: RpcClient) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                rpc,
+                latest: RwLock::new(None),
+                refresh_task: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Returns the cached blockhash, or fetches one if there is none or it is stale
+    pub async fn get_latest_blockhash(&self) -> ClientResult<LatestBlockhash> {
+        self.start_refresh();
+
+        match self.cached().await {
+            Some(latest) if !latest.is_stale() => Ok(latest),
+            _ => self.inner.refresh().await,
+        }
+    }
+
+    /// Returns the cached blockhash, or fetches one if there is none or it is stale
+    pub async fn get_blockhash(&self) -> ClientResult<Hash> {
+        Ok(self.get_latest_blockhash().await?.blockhash)
+    }
+
+    /// Returns the cached blockhash without fetching, None before the first refresh
+    pub async fn cached(&self) -> Option<LatestBlockhash> {
+        *self.inner.latest.read().await
+    }
+
+    /// Fetches a new blockhash into the cache
+    pub async fn refresh(&self) -> ClientResult<LatestBlockhash> {
+        self.inner.refresh().await
+    }
+
+    fn start_refresh(&self) {
+        let inner = Arc::downgrade(&self.inner);
+        self.inner.refresh_task.get_or_init(|| tokio::spawn(refresh_loop(inner)));
+    }
+}
+
+async fn refresh_loop(inner: Weak<Inner>) {
+    let mut interval = tokio::time::interval(BLOCKHASH_REFRESH_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let Some(inner) = inner.upgrade() else {
+            break;
+        };
+        // A failed refresh is retried on the next tick, callers fetch themselves once it is stale
+        let _ = inner.refresh().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use solana_rpc_client::{
+        rpc_client::RpcClientConfig,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    };
+    use solana_rpc_client_api::{
+        client_error::{ErrorKind, Result},
+        request::RpcRequest,
+    };
+
+    use super::*;
+
+    /// Answers `getLatestBlockhash` with a new blockhash, or an error while `fail` is set
+    #[derive(Clone, Default)]
+    struct MockSender {
+        requests: Arc<AtomicUsize>,
+        fail: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl RpcSender for MockSender {
+        async fn send(&self, request: RpcRequest, _params: Value) -> Result<Value> {
+            assert!(matches!(request, RpcRequest::GetLatestBlockhash));
+            let requests = self.requests.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.fail.load(Ordering::SeqCst) {
+                return Err(ErrorKind::Custom("unavailable".to_string()).into());
+            }
+            Ok(json!({
+                "context": { "slot": requests },
+                "value": { "blockhash": Hash::new_unique().to_string(), "lastValidBlockHeight": 150 + requests },
+            }))
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "mock".to_string()
+        }
+    }
+
+    fn blockhash_cache(sender: &MockSender) -> BlockhashCache {
+        BlockhashCache::new_with_rpc(RpcClient::new_sender(sender.clone(), RpcClientConfig::with_commitment(CommitmentConfig::confirmed())))
+    }
+
+    async fn seed(blockhash_cache: &BlockhashCache) -> LatestBlockhash {
+        let latest = LatestBlockhash {
+            blockhash: Hash::new_unique(),
+            last_valid_block_height: 100,
+            fetched_at: Instant::now(),
+        };
+        *blockhash_cache.inner.latest.write().await = Some(latest);
+        latest
+    }
+
+    #[tokio::test]
+    async fn test_returns_cached_blockhash_while_fresh() {
+        tokio::time::pause();
+        let sender = MockSender::default();
+        let blockhash_cache = blockhash_cache(&sender);
+
+        // Any fetch fails, so only the cached blockhash can be returned
+        sender.fail.store(true, Ordering::SeqCst);
+        let latest = seed(&blockhash_cache).await;
+        assert_eq!(blockhash_cache.get_latest_blockhash().await.unwrap(), latest);
+
+        tokio::time::sleep(BLOCKHASH_MAX_AGE - Duration::from_millis(100)).await;
+        assert!(!latest.is_stale());
+        assert_eq!(blockhash_cache.get_latest_blockhash().await.unwrap(), latest);
+        assert_eq!(blockhash_cache.get_blockhash().await.unwrap(), latest.blockhash);
+    }
+
+    #[tokio::test]
+    async fn test_fetches_stale_blockhash() {
+        tokio::time::pause();
+        let sender = MockSender::default();
+        let blockhash_cache = blockhash_cache(&sender);
+
+        sender.fail.store(true, Ordering::SeqCst);
+        let stale = seed(&blockhash_cache).await;
+        tokio::time::sleep(BLOCKHASH_MAX_AGE).await;
+        assert!(stale.is_stale());
+
+        // The stale blockhash is never handed out, the caller fetches and sees the error
+        let requests = sender.requests.load(Ordering::SeqCst);
+        assert!(blockhash_cache.get_latest_blockhash().await.is_err());
+        assert_eq!(sender.requests.load(Ordering::SeqCst), requests + 1);
+
+        sender.fail.store(false, Ordering::SeqCst);
+        let latest = blockhash_cache.get_latest_blockhash().await.unwrap();
+        assert_ne!(latest.blockhash, stale.blockhash);
+        assert_eq!(latest.fetched_at, Instant::now());
+        assert_eq!(blockhash_cache.cached().await, Some(latest));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_task_stops_with_last_clone() {
+        tokio::time::pause();
+        let sender = MockSender::default();
+        let blockhash_cache = blockhash_cache(&sender);
+        let clone = blockhash_cache.clone();
+        let inner = Arc::downgrade(&blockhash_cache.inner);
+
+        blockhash_cache.get_latest_blockhash().await.unwrap();
+        drop(blockhash_cache);
+
+        // The task keeps refreshing for the remaining clone
+        let requests = sender.requests.load(Ordering::SeqCst);
+        tokio::time::sleep(BLOCKHASH_REFRESH_INTERVAL * 3).await;
+        assert!(sender.requests.load(Ordering::SeqCst) > requests);
+        assert!(clone.cached().await.is_some_and(|latest| !latest.is_stale()));
+
+        drop(clone);
+        assert!(inner.upgrade().is_none());
+
+        let requests = sender.requests.load(Ordering::SeqCst);
+        tokio::time::sleep(BLOCKHASH_REFRESH_INTERVAL * 3).await;
+        assert_eq!(sender.requests.load(Ordering::SeqCst), requests);
+    }
+}
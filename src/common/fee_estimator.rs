@@ -0,0 +1,183 @@
+//! Compute unit price estimation
+//!
+//! `getRecentPrioritizationFees` without accounts reports the fees of the whole cluster. Queried
+//! with the bonding curve and the Global PDA, it reports what transactions writing the same
+//! Pump.fun accounts paid to land, which is the fee level a trade competes with.
+//!
+//! Fees are fetched at most once per slot for a set of accounts, later estimates in the same slot
+//! reuse them.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+use tokio::time::Instant;
+
+use crate::{
+    constants::rpc::FEE_ESTIMATE_MAX_AGE,
+    error::{ClientError, ClientResult},
+    trade::common::{get_bonding_curve_pda, get_global_pda},
+};
+
+/// Which of the recent prioritization fees an estimate picks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeeStrategy {
+    /// The nearest-rank percentile, from 0 to 100
+    Percentile(u8),
+    #[default]
+    Median,
+    Max,
+}
+
+impl FeeStrategy {
+    /// Picks a fee from `fees`, None if there are none
+    pub fn select(&self, fees: &[u64]) -> Option<u64> {
+        if fees.is_empty() {
+            return None;
+        }
+
+        let mut fees = fees.to_vec();
+        fees.sort_unstable();
+
+        match *self {
+            Self::Percentile(percentile) => {
+                let rank = (fees.len() * percentile.min(100) as usize).div_ceil(100);
+                Some(fees[rank.saturating_sub(1)])
+            }
+            Self::Median => {
+                let middle = fees.len() / 2;
+                if fees.len().is_multiple_of(2) {
+                    Some(((fees[middle - 1] as u128 + fees[middle] as u128) / 2) as u64)
+                } else {
+                    Some(fees[middle])
+                }
+            }
+            Self::Max => fees.last().copied(),
+        }
+    }
+}
+
+/// Recent prioritization fees of a set of accounts
+#[derive(Debug, Clone)]
+struct CachedFees {
+    /// Newest slot the fees were reported for
+    slot: Slot,
+    fetched_at: Instant,
+    fees: Vec<u64>,
+}
+
+/// Estimates compute unit prices from the fees paid by transactions writing the same accounts
+///
+/// Clones share the cached fees.
+#[derive(Clone, Default)]
+pub struct FeeEstimator {
+    cache: Arc<RwLock<HashMap<Vec<Pubkey>, CachedFees>>>,
+}
+
+impl FeeEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the compute unit price `strategy` picks from the recent fees of `accounts`, None if
+    /// no fees were reported
+    pub async fn estimate(&self, rpc: &RpcClient, accounts: &[Pubkey], strategy: FeeStrategy) -> ClientResult<Option<u64>> {
+        let fees = self.recent_fees(rpc, accounts).await?;
+        Ok(strategy.select(&fees))
+    }
+
+    /// Estimates the compute unit price of a trade of `mint`, from the fees of its bonding curve
+    /// and the Global PDA
+    pub async fn estimate_for_mint(&self, rpc: &RpcClient, mint: &Pubkey, strategy: FeeStrategy) -> ClientResult<Option<u64>> {
+        let bonding_curve_pda = get_bonding_curve_pda(mint)
+            .ok_or(ClientError::BondingCurveNotFound)?;
+        self.estimate(rpc, &[bonding_curve_pda, get_global_pda()], strategy).await
+    }
+
+    /// Returns the slot the cached fees of `accounts` were last reported for
+    pub fn cached_slot(&self, accounts: &[Pubkey]) -> Option<Slot> {
+        self.cache.read().unwrap().get(&cache_key(accounts)).map(|cached| cached.slot)
+    }
+
+    async fn recent_fees(&self, rpc: &RpcClient, accounts: &[Pubkey]) -> ClientResult<Vec<u64>> {
+        let key = cache_key(accounts);
+        if let Some(cached) = self.cache.read().unwrap().get(&key) {
+            if cached.fetched_at.elapsed() < FEE_ESTIMATE_MAX_AGE {
+                return Ok(cached.fees.clone());
+            }
+        }
+
+        let recent_fees = rpc.get_recent_prioritization_fees(accounts).await?;
+        let cached = CachedFees {
+            slot: recent_fees.iter().map(|fee| fee.slot).max().unwrap_or_default(),
+            fetched_at: Instant::now(),
+            fees: recent_fees.iter().map(|fee| fee.prioritization_fee).collect(),
+        };
+        let fees = cached.fees.clone();
+        self.cache.write().unwrap().insert(key, cached);
+
+        Ok(fees)
+    }
+}
+
+/// The same accounts in any order share cached fees
+fn cache_key(accounts: &[Pubkey]) -> Vec<Pubkey> {
+    let mut key = accounts.to_vec();
+    key.sort_unstable();
+    key.dedup();
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_strategy_select() {
+        let fees = [0, 5_000, 1_000, 20_000, 3_000, 10_000, 2_000, 50_000, 4_000, 8_000];
+
+        assert_eq!(FeeStrategy::Percentile(0).select(&fees), Some(0));
+        assert_eq!(FeeStrategy::Percentile(10).select(&fees), Some(0));
+        assert_eq!(FeeStrategy::Percentile(25).select(&fees), Some(2_000));
+        assert_eq!(FeeStrategy::Percentile(75).select(&fees), Some(10_000));
+        assert_eq!(FeeStrategy::Percentile(90).select(&fees), Some(20_000));
+        assert_eq!(FeeStrategy::Percentile(100).select(&fees), Some(50_000));
+        assert_eq!(FeeStrategy::Percentile(200).select(&fees), Some(50_000));
+        assert_eq!(FeeStrategy::Median.select(&fees), Some(4_500));
+        assert_eq!(FeeStrategy::Median.select(&fees[..9]), Some(4_000));
+        assert_eq!(FeeStrategy::Max.select(&fees), Some(50_000));
+        assert_eq!(FeeStrategy::Median.select(&[u64::MAX, u64::MAX]), Some(u64::MAX));
+
+        assert_eq!(FeeStrategy::Median.select(&[]), None);
+        assert_eq!(FeeStrategy::Max.select(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_fee_estimator_reuses_fees_within_slot() {
+        let estimator = FeeEstimator::new();
+        let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+        let accounts = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        assert!(estimator.estimate(&rpc, &accounts, FeeStrategy::Max).await.is_err());
+        assert!(estimator.cached_slot(&accounts).is_none());
+
+        estimator.cache.write().unwrap().insert(cache_key(&accounts), CachedFees {
+            slot: 100,
+            fetched_at: Instant::now(),
+            fees: vec![1_000, 3_000, 2_000],
+        });
+
+        // Cached fees are found for the accounts in any order, without a request
+        let reversed = [accounts[1], accounts[0]];
+        assert_eq!(estimator.cached_slot(&reversed), Some(100));
+        assert_eq!(estimator.estimate(&rpc, &reversed, FeeStrategy::Max).await.unwrap(), Some(3_000));
+        assert_eq!(estimator.clone().estimate(&rpc, &accounts, FeeStrategy::Median).await.unwrap(), Some(2_000));
+
+        // Once the slot has passed the fees are fetched again
+        tokio::time::sleep(FEE_ESTIMATE_MAX_AGE).await;
+        assert!(estimator.estimate(&rpc, &accounts, FeeStrategy::Max).await.is_err());
+    }
+}
@@ -3,3 +3,7 @@ pub mod logs_parser;
 pub mod logs_filters;
 pub mod logs_subscribe;
 pub mod logs_events;
+pub mod blockhash_cache;
+pub mod account_cache;
+pub mod rpc_pool;
+pub mod fee_estimator;
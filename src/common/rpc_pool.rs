@@ -0,0 +1,383 @@
+//! RPC endpoint pool
+//!
+//! `RpcPool` puts several RPC endpoints behind one `RpcSender`, so an `RpcClient` built on it
+//! keeps working when a provider rate-limits or falls behind. Reads go to the fastest healthy
+//! endpoint and fail over to the next one on transport errors, and `sendTransaction` is broadcast
+//! to every endpoint at once.
+//!
+//! Endpoints are checked with `getHealth` and `getSlot` in the background from the first request.
+//! An endpoint is unhealthy when a check fails or its slot trails the most advanced endpoint by
+//! more than `RPC_MAX_SLOT_LAG`.
+
+use std::{
+    sync::{Arc, OnceLock, RwLock, Weak},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::{future::join_all, stream::FuturesUnordered, StreamExt};
+use serde_json::{json, Value};
+use solana_rpc_client::{
+    http_sender::HttpSender,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client_api::{
+    client_error::{Error, ErrorKind, Result},
+    custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    request::{RpcError, RpcRequest},
+};
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig};
+use tokio::task::JoinHandle;
+
+use crate::{
+    constants::rpc::{RPC_HEALTH_CHECK_INTERVAL, RPC_MAX_SLOT_LAG},
+    error::{ClientError, ClientResult},
+};
+
+/// Health and transport statistics of one endpoint of an RPC pool
+#[derive(Clone)]
+pub struct EndpointStats {
+    pub url: String,
+    pub healthy: bool,
+    /// Processed slot at the last health check
+    pub slot: Option<Slot>,
+    /// Slots behind the most advanced endpoint at the last health check
+    pub slot_lag: Option<u64>,
+    /// `getHealth` round trip at the last health check
+    pub latency: Option<Duration>,
+    pub transport: RpcTransportStats,
+}
+
+/// Result of the last health check of an endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Health {
+    healthy: bool,
+    slot: Option<Slot>,
+    slot_lag: Option<u64>,
+    latency: Option<Duration>,
+}
+
+impl Default for Health {
+    /// Endpoints are used in the order they were given until they are first checked
+    fn default() -> Self {
+        Self {
+            healthy: true,
+            slot: None,
+            slot_lag: None,
+            latency: None,
+        }
+    }
+}
+
+struct Endpoint {
+    sender: HttpSender,
+    health: RwLock<Health>,
+}
+
+impl Endpoint {
+    async fn check_health(&self) -> Health {
+        let start = Instant::now();
+        let health = self.sender.send(RpcRequest::GetHealth, Value::Null).await;
+        let latency = start.elapsed();
+
+        let slot = self.sender
+            .send(RpcRequest::GetSlot, json!([CommitmentConfig::processed()]))
+            .await
+            .ok()
+            .and_then(|slot| slot.as_u64());
+
+        Health {
+            healthy: health.is_ok() && slot.is_some(),
+            slot,
+            slot_lag: None,
+            latency: Some(latency),
+        }
+    }
+}
+
+/// Pool of RPC endpoints used as the transport of an `RpcClient`
+///
+/// Clones share the endpoints and their health checks. The health checks stop when the last
+/// clone is dropped.
+#[derive(Clone)]
+pub struct RpcPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    endpoints: Vec<Arc<Endpoint>>,
+    health_task: OnceLock<JoinHandle<()>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(task) = self.health_task.get() {
+            task.abort();
+        }
+    }
+}
+
+impl Inner {
+    async fn check_health(&self) {
+        let mut healths = join_all(self.endpoints.iter().map(|endpoint| endpoint.check_health())).await;
+        apply_slot_lag(&mut healths);
+
+        for (endpoint, health) in self.endpoints.iter().zip(healths) {
+            *endpoint.health.write().unwrap() = health;
+        }
+    }
+
+    /// Returns the endpoints in the order reads should try them
+    fn ranked_endpoints(&self) -> Vec<Arc<Endpoint>> {
+        let healths: Vec<Health> = self.endpoints
+            .iter()
+            .map(|endpoint| *endpoint.health.read().unwrap())
+            .collect();
+
+        rank_endpoints(&healths)
+            .into_iter()
+            .map(|index| self.endpoints[index].clone())
+            .collect()
+    }
+
+    async fn send_with_failover(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        let mut last_error = None;
+        for endpoint in self.ranked_endpoints() {
+            match endpoint.sender.send(request, params.clone()).await {
+                Err(err) if is_endpoint_error(&err) => {
+                    // Skip the endpoint for other reads until the next health check
+                    endpoint.health.write().unwrap().healthy = false;
+                    last_error = Some(err);
+                }
+                result => return result,
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| no_endpoints_error(request)))
+    }
+
+    async fn broadcast(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        // Each send runs in its own task, so the slower endpoints still receive the request after
+        // the first one answers
+        let mut sends: FuturesUnordered<_> = self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let endpoint = endpoint.clone();
+                let params = params.clone();
+                tokio::spawn(async move { endpoint.sender.send(request, params).await })
+            })
+            .collect();
+
+        let mut first_error = None;
+        while let Some(result) = sends.next().await {
+            match result {
+                Ok(Ok(value)) => return Ok(value),
+                Ok(Err(err)) => {
+                    first_error.get_or_insert(err);
+                }
+                Err(err) => {
+                    first_error.get_or_insert(Error::new_with_request(ErrorKind::Custom(err.to_string()), request));
+                }
+            }
+        }
+
+        Err(first_error.unwrap_or_else(|| no_endpoints_error(request)))
+    }
+}
+
+impl RpcPool {
+    /// Creates a pool of the given HTTP endpoints, each with the default timeout of 30 seconds
+    pub fn new(rpc_urls: Vec<String>) -> ClientResult<Self> {
+        if rpc_urls.is_empty() {
+            return Err(ClientError::InvalidInput("RPC pool needs at least one endpoint"));
+        }
+
+        let endpoints = rpc_urls
+            .into_iter()
+            .map(|url| Arc::new(Endpoint {
+                sender: HttpSender::new(url),
+                health: RwLock::new(Health::default()),
+            }))
+            .collect();
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                endpoints,
+                health_task: OnceLock::new(),
+            }),
+        })
+    }
+
+    /// Checks every endpoint now instead of waiting for the next background check
+    pub async fn check_health(&self) {
+        self.inner.check_health().await;
+    }
+
+    /// Returns the health and transport statistics of every endpoint
+    pub fn endpoint_stats(&self) -> Vec<EndpointStats> {
+        self.inner.endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = *endpoint.health.read().unwrap();
+                EndpointStats {
+                    url: endpoint.sender.url(),
+                    healthy: health.healthy,
+                    slot: health.slot,
+                    slot_lag: health.slot_lag,
+                    latency: health.latency,
+                    transport: endpoint.sender.get_transport_stats(),
+                }
+            })
+            .collect()
+    }
+
+    fn start_health_checks(&self) {
+        let inner = Arc::downgrade(&self.inner);
+        self.inner.health_task.get_or_init(|| tokio::spawn(health_check_loop(inner)));
+    }
+}
+
+#[async_trait]
+impl RpcSender for RpcPool {
+    async fn send(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        self.start_health_checks();
+
+        match request {
+            RpcRequest::SendTransaction => self.inner.broadcast(request, params).await,
+            _ => self.inner.send_with_failover(request, params).await,
+        }
+    }
+
+    /// Returns the statistics of all endpoints added together
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.endpoints
+            .iter()
+            .map(|endpoint| endpoint.sender.get_transport_stats())
+            .fold(RpcTransportStats::default(), |mut total, stats| {
+                total.request_count += stats.request_count;
+                total.elapsed_time += stats.elapsed_time;
+                total.rate_limited_time += stats.rate_limited_time;
+                total
+            })
+    }
+
+    /// Returns the URL of the first endpoint
+    fn url(&self) -> String {
+        self.inner.endpoints[0].sender.url()
+    }
+}
+
+async fn health_check_loop(inner: Weak<Inner>) {
+    let mut interval = tokio::time::interval(RPC_HEALTH_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let Some(inner) = inner.upgrade() else {
+            break;
+        };
+        inner.check_health().await;
+    }
+}
+
+/// Marks endpoints that trail the most advanced endpoint by more than `RPC_MAX_SLOT_LAG` as
+/// unhealthy
+fn apply_slot_lag(healths: &mut [Health]) {
+    let Some(max_slot) = healths.iter().filter_map(|health| health.slot).max() else {
+        return;
+    };
+
+    for health in healths {
+        health.slot_lag = health.slot.map(|slot| max_slot.saturating_sub(slot));
+        if health.slot_lag.is_some_and(|slot_lag| slot_lag > RPC_MAX_SLOT_LAG) {
+            health.healthy = false;
+        }
+    }
+}
+
+/// Returns endpoint indexes with healthy endpoints first, each group by ascending latency
+fn rank_endpoints(healths: &[Health]) -> Vec<usize> {
+    let mut indexes: Vec<usize> = (0..healths.len()).collect();
+    // The sort is stable, so unchecked endpoints keep the order they were given in
+    indexes.sort_by_key(|&index| {
+        let health = &healths[index];
+        (!health.healthy, health.latency.unwrap_or(Duration::MAX))
+    });
+    indexes
+}
+
+/// Returns true if the error comes from the endpoint rather than the request, so another
+/// endpoint may answer it
+fn is_endpoint_error(err: &Error) -> bool {
+    match &err.kind {
+        ErrorKind::Io(_) | ErrorKind::Reqwest(_) | ErrorKind::Middleware(_) => true,
+        ErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+        _ => false,
+    }
+}
+
+fn no_endpoints_error(request: RpcRequest) -> Error {
+    Error::new_with_request(ErrorKind::Custom("No RPC endpoints".to_string()), request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(healthy: bool, slot: Option<Slot>, latency_ms: Option<u64>) -> Health {
+        Health {
+            healthy,
+            slot,
+            slot_lag: None,
+            latency: latency_ms.map(Duration::from_millis),
+        }
+    }
+
+    #[test]
+    fn test_apply_slot_lag() {
+        let mut healths = vec![
+            health(true, Some(1_000), Some(50)),
+            health(true, Some(1_000 - RPC_MAX_SLOT_LAG), Some(20)),
+            health(true, Some(999 - RPC_MAX_SLOT_LAG), Some(10)),
+            health(false, None, Some(5)),
+        ];
+        apply_slot_lag(&mut healths);
+
+        assert_eq!(healths[0].slot_lag, Some(0));
+        assert!(healths[0].healthy);
+        assert_eq!(healths[1].slot_lag, Some(RPC_MAX_SLOT_LAG));
+        assert!(healths[1].healthy);
+        assert_eq!(healths[2].slot_lag, Some(RPC_MAX_SLOT_LAG + 1));
+        assert!(!healths[2].healthy);
+        assert_eq!(healths[3].slot_lag, None);
+        assert!(!healths[3].healthy);
+
+        assert_eq!(rank_endpoints(&healths), vec![1, 0, 3, 2]);
+    }
+
+    #[test]
+    fn test_rank_unchecked_endpoints() {
+        let healths = vec![Health::default(); 3];
+        assert_eq!(rank_endpoints(&healths), vec![0, 1, 2]);
+
+        let healths = vec![Health::default(), health(true, Some(10), Some(30)), health(false, Some(10), Some(1))];
+        assert_eq!(rank_endpoints(&healths), vec![1, 0, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_failover_marks_endpoints_unhealthy() {
+        let pool = RpcPool::new(vec![
+            "http://127.0.0.1:1".to_string(),
+            "http://127.0.0.1:2".to_string(),
+        ]).unwrap();
+
+        let result = pool.inner.send_with_failover(RpcRequest::GetSlot, Value::Null).await;
+        assert!(result.is_err_and(|err| is_endpoint_error(&err)));
+
+        let stats = pool.endpoint_stats();
+        assert!(stats.iter().all(|stats| !stats.healthy && stats.transport.request_count == 1));
+        assert_eq!(pool.get_transport_stats().request_count, 2);
+        assert_eq!(pool.url(), "http://127.0.0.1:1");
+
+        assert!(RpcPool::new(Vec::new()).is_err());
+    }
+}
@@ -9,6 +9,9 @@
 //!
 //! - `seeds`: Contains seed values used for PDA derivation
 //! - `accounts`: Contains important program account addresses
+//! - `trade`: Contains trade defaults such as slippage and compute budget
+//! - `jito`: Contains Jito bundle submission settings
+//! - `rpc`: Contains RPC pool health check and fee estimation settings
 
 /// Constants used as seeds for deriving PDAs (Program Derived Addresses)
 pub mod seeds {
@@ -53,8 +56,77 @@ pub mod accounts {
 }
 
 pub mod trade {
+    use std::time::Duration;
+
     pub const JITO_TIP_AMOUNT: f64 = 0.0001;
     pub const DEFAULT_SLIPPAGE: u64 = 3000; // 30%
     pub const DEFAULT_COMPUTE_UNIT_LIMIT: u32 = 78000;
     pub const DEFAULT_COMPUTE_UNIT_PRICE: u64 = 500000;
+    /// Maximum serialized size of a transaction, the packet data size of the network
+    pub const MAX_TRANSACTION_SIZE: usize = 1232;
+    /// Interval between background refreshes of the cached blockhash
+    pub const BLOCKHASH_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+    /// Age after which a cached blockhash is fetched again before use, well within the 150
+    /// blocks a blockhash stays valid
+    pub const BLOCKHASH_MAX_AGE: Duration = Duration::from_secs(10);
+    /// Delay before an ended account subscription is opened again
+    pub const ACCOUNT_RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+    /// Slots a cached account may lag behind the current slot before it is fetched again
+    pub const ACCOUNT_MAX_SLOT_LAG: u64 = 50;
+    /// Interval between rebroadcasts of a transaction sent over RPC, until it lands or expires
+    pub const REBROADCAST_INTERVAL: Duration = Duration::from_millis(1000);
+    /// Time after which a transaction sent over RPC is given up on if neither its confirmation nor
+    /// its expiry could be read, the lifetime of a blockhash at the default slot time
+    pub const SUBMIT_TIMEOUT: Duration =
+        Duration::from_millis(solana_sdk::clock::MAX_PROCESSING_AGE as u64 * solana_sdk::clock::DEFAULT_MS_PER_SLOT);
+    /// Times the transaction of a confirmed signature is read before the outcome is taken from
+    /// the signature status alone
+    pub const OUTCOME_FETCH_ATTEMPTS: u32 = 5;
+    /// Default number of attempts of a retried trade
+    pub const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+    /// Default slippage added to each retry of a trade, in basis points
+    pub const DEFAULT_RETRY_SLIPPAGE_STEP: u64 = 500; // 5%
+    /// Default largest move of the curve price from the first attempt before retries stop, in
+    /// basis points
+    pub const DEFAULT_RETRY_MAX_PRICE_MOVE: u64 = 2000; // 20%
+    /// Default time after the first attempt of a trade during which retries may start
+    pub const DEFAULT_RETRY_DEADLINE: Duration = Duration::from_secs(60);
+}
+
+pub mod jito {
+    use std::time::Duration;
+
+    /// Interval between bundle status polls while waiting for a bundle to land
+    pub const BUNDLE_STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+    /// Default time to wait for a bundle to land
+    pub const BUNDLE_CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+    /// Maximum number of transactions the block engine accepts in one bundle
+    pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+    /// Mainnet block engine bundle endpoints in Amsterdam, Frankfurt, New York, Tokyo and Salt
+    /// Lake City
+    pub const MAINNET_BLOCK_ENGINE_URLS: [&str; 5] = [
+        "https://amsterdam.mainnet.block-engine.jito.wtf/api/v1/bundles",
+        "https://frankfurt.mainnet.block-engine.jito.wtf/api/v1/bundles",
+        "https://ny.mainnet.block-engine.jito.wtf/api/v1/bundles",
+        "https://tokyo.mainnet.block-engine.jito.wtf/api/v1/bundles",
+        "https://slc.mainnet.block-engine.jito.wtf/api/v1/bundles",
+    ];
+    /// Jito API reporting recently landed tip percentiles
+    pub const TIP_FLOOR_URL: &str = "https://bundles.jito.wtf/api/v1/bundles/tip_floor";
+    /// Age after which tip floor percentiles are fetched again
+    pub const TIP_FLOOR_MAX_AGE: Duration = Duration::from_secs(10);
+    /// Default minimum interval between requests to one block engine region, its default limit
+    /// of one request per second
+    pub const REGION_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+}
+
+pub mod rpc {
+    use std::time::Duration;
+
+    /// Interval between health checks of the endpoints of an RPC pool
+    pub const RPC_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+    /// Slots an endpoint may trail the most advanced endpoint of its pool before it is unhealthy
+    pub const RPC_MAX_SLOT_LAG: u64 = 10;
+    /// How long estimated prioritization fees are reused, about one slot
+    pub const FEE_ESTIMATE_MAX_AGE: Duration = Duration::from_millis(solana_sdk::clock::DEFAULT_MS_PER_SLOT);
 }
@@ -8,21 +8,38 @@
 //! # Error Types
 //!
 //! - `BondingCurveNotFound`: The bonding curve account was not found.
+//! - `BondingCurveComplete`: The bonding curve is complete and can no longer be traded.
 //! - `BondingCurveError`: An error occurred while interacting with the bonding curve.
 //! - `BorshError`: An error occurred while serializing or deserializing data using Borsh.
 //! - `SolanaClientError`: An error occurred while interacting with the Solana RPC client.
 //! - `UploadMetadataError`: An error occurred while uploading metadata to IPFS.
 //! - `InvalidInput`: Invalid input parameters were provided.
 //! - `InsufficientFunds`: Insufficient funds for a transaction.
+//! - `InsufficientBalance`: The wallet balance cannot cover the requested amount.
 //! - `SimulationError`: Transaction simulation failed.
+//! - `TransactionFailed`: The transaction was rejected when sent.
+//! - `SlippageExceeded`: The trade was rejected because the price moved beyond the slippage bound.
 //! - `RateLimitExceeded`: Rate limit exceeded.
+//! - `JitoBundleRejected`: The Jito block engine rejected a bundle.
+//! - `JitoBundleFailed`: A bundle was accepted by the Jito block engine but failed to land.
+//! - `JitoClientError`: An error occurred while talking to the Jito block engine.
+//!
+//! Simulation and send failures carry a `TransactionFailure` with the failing instruction index,
+//! the decoded `PumpFunProgramError` and the program logs.
+
+mod program_error;
+
+pub use program_error::*;
 
 use serde_json::Error;
 use solana_client::{
     client_error::ClientError as SolanaClientError, 
     pubsub_client::PubsubClientError
 };
-use solana_sdk::pubkey::ParsePubkeyError;
+use solana_rpc_client_api::{client_error::ErrorKind, request::RpcError};
+use solana_sdk::{message::CompileError, pubkey::ParsePubkeyError, signer::SignerError};
+
+use crate::jito::{api::BundleSimulationFailure, client_error::Error as JitoClientError, request::RpcRequest};
 
 // #[derive(Debug)]
 // #[allow(dead_code)]
@@ -41,22 +58,38 @@ use solana_sdk::pubkey::ParsePubkeyError;
 pub enum ClientError {
     /// Bonding curve account was not found
     BondingCurveNotFound,
+    /// Bonding curve is complete and can no longer be traded
+    BondingCurveComplete,
     /// Error related to bonding curve operations
     BondingCurveError(&'static str),
     /// Error deserializing data using Borsh
     BorshError(std::io::Error),
     /// Error from Solana RPC client
-    SolanaClientError(solana_client::client_error::ClientError),
+    SolanaClientError(Box<SolanaClientError>),
     /// Error uploading metadata
-    UploadMetadataError(Box<dyn std::error::Error>),
+    UploadMetadataError(Box<dyn std::error::Error + Send + Sync>),
     /// Invalid input parameters
     InvalidInput(&'static str),
     /// Insufficient funds for transaction
     InsufficientFunds,
+    /// Wallet balance cannot cover the requested amount
+    InsufficientBalance { required: u64, available: u64 },
     /// Transaction simulation failed
-    SimulationError(String),
+    SimulationError(Box<TransactionFailure>),
+    /// Transaction was rejected when sent
+    TransactionFailed(Box<TransactionFailure>),
+    /// Price moved beyond the slippage bound
+    SlippageExceeded(Box<TransactionFailure>),
     /// Rate limit exceeded
     RateLimitExceeded,
+    /// Jito block engine rejected the bundle
+    JitoBundleRejected { code: i64, message: String },
+    /// Jito bundle was accepted but failed to land
+    JitoBundleFailed(String),
+    /// Jito bundle failed simulation and was not sent
+    JitoBundleSimulationFailed(Box<BundleSimulationFailure>),
+    /// Error from the Jito block engine client
+    JitoClientError(Box<JitoClientError>),
 
     OrderLimitExceeded,
 
@@ -101,13 +134,24 @@ impl std::fmt::Display for ClientError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::BondingCurveNotFound => write!(f, "Bonding curve not found"),
+            Self::BondingCurveComplete => write!(f, "Bonding curve is complete"),
             Self::BondingCurveError(msg) => write!(f, "Bonding curve error: {}", msg),
             Self::BorshError(err) => write!(f, "Borsh serialization error: {}", err),
             Self::SolanaClientError(err) => write!(f, "Solana client error: {}", err),
             Self::UploadMetadataError(err) => write!(f, "Metadata upload error: {}", err),
             Self::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
             Self::InsufficientFunds => write!(f, "Insufficient funds for transaction"),
-            Self::SimulationError(msg) => write!(f, "Transaction simulation failed: {}", msg),
+            Self::InsufficientBalance { required, available } => write!(f, "Insufficient balance: {} required, {} available", required, available),
+            Self::SimulationError(failure) => write!(f, "Transaction simulation failed: {}", failure),
+            Self::TransactionFailed(failure) => write!(f, "Transaction failed: {}", failure),
+            Self::SlippageExceeded(failure) => write!(f, "Slippage exceeded: {}", failure),
+            Self::JitoBundleRejected { code, message } => write!(f, "Jito bundle rejected: {} (code {})", message, code),
+            Self::JitoBundleFailed(bundle_id) => write!(f, "Jito bundle failed to land: {}", bundle_id),
+            Self::JitoBundleSimulationFailed(failure) => match (failure.transaction_index, &failure.failure) {
+                (Some(index), Some(transaction_failure)) => write!(f, "Jito bundle simulation failed at transaction {}: {}", index, transaction_failure),
+                _ => write!(f, "Jito bundle simulation failed: {}", failure.error),
+            },
+            Self::JitoClientError(err) => write!(f, "Jito client error: {}", err),
             Self::ExternalService(msg) => write!(f, "External service error: {}", msg),
             Self::RateLimitExceeded => write!(f, "Rate limit exceeded"),
             Self::OrderLimitExceeded => write!(f, "Order limit exceeded"),
@@ -135,8 +179,9 @@ impl std::error::Error for ClientError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::BorshError(err) => Some(err),
-            Self::SolanaClientError(err) => Some(err),
+            Self::SolanaClientError(err) => Some(err.as_ref()),
             Self::UploadMetadataError(err) => Some(err.as_ref()),
+            Self::JitoClientError(err) => Some(err.as_ref()),
             Self::ExternalService(_) => None,
             Self::Redis(_, _) => None,
             Self::Solana(_, _) => None,
@@ -158,12 +203,60 @@ impl std::error::Error for ClientError {
     }
 }
 
+impl ClientError {
+    /// Maps a failed simulation to `SlippageExceeded` or `SimulationError`
+    pub fn simulation_failed(failure: TransactionFailure) -> Self {
+        if failure.is_slippage() {
+            ClientError::SlippageExceeded(Box::new(failure))
+        } else {
+            ClientError::SimulationError(Box::new(failure))
+        }
+    }
+
+    /// Maps a failed send to `SlippageExceeded` or `TransactionFailed`
+    pub fn transaction_failed(failure: TransactionFailure) -> Self {
+        if failure.is_slippage() {
+            ClientError::SlippageExceeded(Box::new(failure))
+        } else {
+            ClientError::TransactionFailed(Box::new(failure))
+        }
+    }
+
+    /// Returns the details of a failed simulation or send
+    pub fn transaction_failure(&self) -> Option<&TransactionFailure> {
+        match self {
+            Self::SimulationError(failure) | Self::TransactionFailed(failure) | Self::SlippageExceeded(failure) => Some(failure),
+            _ => None,
+        }
+    }
+
+    /// Returns the Pump.fun program error that rejected the transaction
+    pub fn program_error(&self) -> Option<PumpFunProgramError> {
+        self.transaction_failure().and_then(|failure| failure.program_error)
+    }
+}
+
 impl From<SolanaClientError> for ClientError {
     fn from(error: SolanaClientError) -> Self {
-        ClientError::Solana(
-            "Solana client error".to_string(),
-            error.to_string(),
-        )
+        match TransactionFailure::from_client_error(&error) {
+            Some(failure) => ClientError::transaction_failed(failure),
+            None => ClientError::SolanaClientError(Box::new(error)),
+        }
+    }
+}
+
+impl From<JitoClientError> for ClientError {
+    fn from(error: JitoClientError) -> Self {
+        if let (Some(RpcRequest::SendBundle), ErrorKind::RpcError(RpcError::RpcResponseError { code, message, .. })) =
+            (error.request(), error.kind())
+        {
+            return ClientError::JitoBundleRejected {
+                code: *code,
+                message: message.clone(),
+            };
+        }
+
+        ClientError::JitoClientError(Box::new(error))
     }
 }
 
@@ -185,6 +278,24 @@ impl From<ParsePubkeyError> for ClientError {
     }
 }
 
+impl From<CompileError> for ClientError {
+    fn from(error: CompileError) -> Self {
+        ClientError::Solana(
+            "Message compile error".to_string(),
+            error.to_string(),
+        )
+    }
+}
+
+impl From<SignerError> for ClientError {
+    fn from(error: SignerError) -> Self {
+        ClientError::Solana(
+            "Signer error".to_string(),
+            error.to_string(),
+        )
+    }
+}
+
 impl From<Error> for ClientError {
     fn from(err: Error) -> Self {
         ClientError::Parse(
@@ -0,0 +1,237 @@
+//! Pump.fun program errors
+//!
+//! This module maps the custom error codes returned by the Pump.fun program to typed errors, and
+//! captures the details of failed simulations and sends.
+//!
+//! # Types
+//!
+//! - `PumpFunProgramError`: The error table of the Pump.fun program (codes 6000-6008).
+//! - `TransactionFailure`: The failing instruction, decoded program error and logs of a failed transaction.
+
+use solana_client::client_error::ClientError as SolanaClientError;
+use solana_rpc_client_api::{
+    client_error::ErrorKind,
+    request::{RpcError, RpcResponseErrorData},
+    response::RpcSimulateTransactionResult,
+};
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+use crate::constants::accounts::PUMPFUN;
+
+/// Custom errors returned by the Pump.fun program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PumpFunProgramError {
+    /// The given account is not authorized to execute this instruction
+    NotAuthorized,
+    /// The program is already initialized
+    AlreadyInitialized,
+    /// Slippage: too much SOL required to buy the given amount of tokens
+    TooMuchSolRequired,
+    /// Slippage: too little SOL received to sell the given amount of tokens
+    TooLittleSolReceived,
+    /// The mint does not match the bonding curve
+    MintDoesNotMatchBondingCurve,
+    /// The bonding curve has completed and liquidity migrated to raydium
+    BondingCurveComplete,
+    /// The bonding curve has not completed
+    BondingCurveNotComplete,
+    /// The program is not initialized
+    NotInitialized,
+    /// Withdraw too frequent
+    WithdrawTooFrequent,
+}
+
+impl PumpFunProgramError {
+    /// First custom error code used by the program
+    pub const FIRST_CODE: u32 = 6000;
+
+    const ALL: [Self; 9] = [
+        Self::NotAuthorized,
+        Self::AlreadyInitialized,
+        Self::TooMuchSolRequired,
+        Self::TooLittleSolReceived,
+        Self::MintDoesNotMatchBondingCurve,
+        Self::BondingCurveComplete,
+        Self::BondingCurveNotComplete,
+        Self::NotInitialized,
+        Self::WithdrawTooFrequent,
+    ];
+
+    /// Looks up a custom error code in the program's error table
+    pub fn from_code(code: u32) -> Option<Self> {
+        let index = code.checked_sub(Self::FIRST_CODE)?;
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Returns the custom error code of this error
+    pub fn code(&self) -> u32 {
+        Self::FIRST_CODE + *self as u32
+    }
+
+    /// Returns the error name as declared in the program IDL
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NotAuthorized => "NotAuthorized",
+            Self::AlreadyInitialized => "AlreadyInitialized",
+            Self::TooMuchSolRequired => "TooMuchSolRequired",
+            Self::TooLittleSolReceived => "TooLittleSolReceived",
+            Self::MintDoesNotMatchBondingCurve => "MintDoesNotMatchBondingCurve",
+            Self::BondingCurveComplete => "BondingCurveComplete",
+            Self::BondingCurveNotComplete => "BondingCurveNotComplete",
+            Self::NotInitialized => "NotInitialized",
+            Self::WithdrawTooFrequent => "WithdrawTooFrequent",
+        }
+    }
+
+    /// Returns the error message as declared in the program IDL
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::NotAuthorized => "The given account is not authorized to execute this instruction.",
+            Self::AlreadyInitialized => "The program is already initialized.",
+            Self::TooMuchSolRequired => "slippage: Too much SOL required to buy the given amount of tokens.",
+            Self::TooLittleSolReceived => "slippage: Too little SOL received to sell the given amount of tokens.",
+            Self::MintDoesNotMatchBondingCurve => "The mint does not match the bonding curve.",
+            Self::BondingCurveComplete => "The bonding curve has completed and liquidity migrated to raydium.",
+            Self::BondingCurveNotComplete => "The bonding curve has not completed.",
+            Self::NotInitialized => "The program is not initialized.",
+            Self::WithdrawTooFrequent => "Withdraw too frequent",
+        }
+    }
+
+    /// Returns true if the trade was rejected by its slippage bound
+    pub fn is_slippage(&self) -> bool {
+        matches!(self, Self::TooMuchSolRequired | Self::TooLittleSolReceived)
+    }
+}
+
+impl std::fmt::Display for PumpFunProgramError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.name(), self.code(), self.message())
+    }
+}
+
+/// Details of a transaction that failed in simulation or when sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionFailure {
+    /// Error reported by the runtime
+    pub error: TransactionError,
+    /// Index of the failing instruction, if the error came from an instruction
+    pub instruction_index: Option<u8>,
+    /// Decoded Pump.fun program error, if the Pump.fun program failed the instruction
+    pub program_error: Option<PumpFunProgramError>,
+    /// Program logs, empty if the RPC node did not return any
+    pub logs: Vec<String>,
+}
+
+impl TransactionFailure {
+    /// Decodes a transaction error and its logs
+    ///
+    /// A custom error code is only decoded as a Pump.fun error if the logs show the Pump.fun
+    /// program failing, or if there are no logs to tell which program failed.
+    pub fn new(error: TransactionError, logs: Vec<String>) -> Self {
+        let (instruction_index, program_error) = match &error {
+            TransactionError::InstructionError(index, InstructionError::Custom(code)) => {
+                let failed_prefix = format!("Program {} failed", PUMPFUN);
+                let program_error = if logs.is_empty() || logs.iter().any(|log| log.starts_with(&failed_prefix)) {
+                    PumpFunProgramError::from_code(*code)
+                } else {
+                    None
+                };
+                (Some(*index), program_error)
+            }
+            TransactionError::InstructionError(index, _) => (Some(*index), None),
+            _ => (None, None),
+        };
+
+        Self {
+            error,
+            instruction_index,
+            program_error,
+            logs,
+        }
+    }
+
+    /// Returns the failure of a simulation, or None if the simulation succeeded
+    pub fn from_simulation(result: &RpcSimulateTransactionResult) -> Option<Self> {
+        let error = result.err.clone()?;
+        Some(Self::new(error, result.logs.clone().unwrap_or_default()))
+    }
+
+    /// Returns the transaction failure behind a Solana client error, if there is one
+    ///
+    /// Covers preflight failures, which carry the simulation logs, and transactions that failed
+    /// after being sent.
+    pub fn from_client_error(error: &SolanaClientError) -> Option<Self> {
+        match error.kind() {
+            ErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+                ..
+            }) => Self::from_simulation(result),
+            ErrorKind::TransactionError(error) => Some(Self::new(error.clone(), Vec::new())),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the trade was rejected by its slippage bound
+    pub fn is_slippage(&self) -> bool {
+        self.program_error.is_some_and(|err| err.is_slippage())
+    }
+}
+
+impl std::fmt::Display for TransactionFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.program_error, self.instruction_index) {
+            (Some(program_error), Some(index)) => write!(f, "instruction {}: {}", index, program_error),
+            _ => write!(f, "{}", self.error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program_error_codes() {
+        for error in PumpFunProgramError::ALL {
+            assert_eq!(PumpFunProgramError::from_code(error.code()), Some(error));
+        }
+
+        assert_eq!(PumpFunProgramError::from_code(6000), Some(PumpFunProgramError::NotAuthorized));
+        assert_eq!(PumpFunProgramError::from_code(6002), Some(PumpFunProgramError::TooMuchSolRequired));
+        assert_eq!(PumpFunProgramError::from_code(6005), Some(PumpFunProgramError::BondingCurveComplete));
+        assert_eq!(PumpFunProgramError::from_code(5999), None);
+        assert_eq!(PumpFunProgramError::from_code(6009), None);
+        assert_eq!(PumpFunProgramError::from_code(0), None);
+    }
+
+    #[test]
+    fn test_transaction_failure() {
+        let logs = vec![
+            format!("Program {} invoke [1]", PUMPFUN),
+            "Program log: Instruction: Buy".to_string(),
+            format!("Program {} failed: custom program error: 0x1772", PUMPFUN),
+        ];
+        let failure = TransactionFailure::new(
+            TransactionError::InstructionError(2, InstructionError::Custom(6002)),
+            logs.clone(),
+        );
+        assert_eq!(failure.instruction_index, Some(2));
+        assert_eq!(failure.program_error, Some(PumpFunProgramError::TooMuchSolRequired));
+        assert_eq!(failure.logs, logs);
+        assert!(failure.is_slippage());
+
+        // Custom error from another program
+        let failure = TransactionFailure::new(
+            TransactionError::InstructionError(1, InstructionError::Custom(6002)),
+            vec!["Program 11111111111111111111111111111111 failed: custom program error: 0x1772".to_string()],
+        );
+        assert_eq!(failure.instruction_index, Some(1));
+        assert_eq!(failure.program_error, None);
+
+        let failure = TransactionFailure::new(TransactionError::BlockhashNotFound, Vec::new());
+        assert_eq!(failure.instruction_index, None);
+        assert_eq!(failure.program_error, None);
+        assert!(!failure.is_slippage());
+    }
+}
@@ -10,14 +10,12 @@
 //! - `buy`: Instruction to buy tokens from a bonding curve by providing SOL.
 //! - `sell`: Instruction to sell tokens back to the bonding curve in exchange for SOL.
 
-use crate::{constants, trade::common::{get_bonding_curve_pda, get_global_pda, get_metadata_pda, get_mint_authority_pda}, PumpFun};
+use crate::{constants, trade::common::{get_bonding_curve_pda, get_global_pda, get_metadata_pda, get_mint_authority_pda}};
 use spl_associated_token_account::get_associated_token_address;
 
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
-    signature::Keypair,
-    signer::Signer,
 };
 
 pub struct Create {
@@ -86,30 +84,30 @@ impl Sell {
 ///
 /// # Arguments
 ///
-/// * `payer` - Keypair that will pay for account creation and transaction fees
-/// * `mint` - Keypair for the new token mint account that will be created
+/// * `payer` - Public key of the account that will pay for account creation and transaction fees
+/// * `mint` - Public key of the new token mint account that will be created, which must sign
 /// * `args` - Create instruction data containing token name, symbol and metadata URI
 ///
 /// # Returns
 ///
 /// Returns a Solana instruction that when executed will create the token and its accounts
-pub fn create(payer: &Keypair, mint: &Keypair, args: Create) -> Instruction {
-    let bonding_curve: Pubkey = get_bonding_curve_pda(&mint.pubkey()).unwrap();
+pub fn create(payer: &Pubkey, mint: &Pubkey, args: Create) -> Instruction {
+    let bonding_curve: Pubkey = get_bonding_curve_pda(mint).unwrap();
     Instruction::new_with_bytes(
         constants::accounts::PUMPFUN,
         &args.data(),
         vec![
-            AccountMeta::new(mint.pubkey(), true),
+            AccountMeta::new(*mint, true),
             AccountMeta::new(get_mint_authority_pda(), false),
             AccountMeta::new(bonding_curve, false),
             AccountMeta::new(
-                get_associated_token_address(&bonding_curve, &mint.pubkey()),
+                get_associated_token_address(&bonding_curve, mint),
                 false,
             ),
             AccountMeta::new_readonly(get_global_pda(), false),
             AccountMeta::new_readonly(constants::accounts::MPL_TOKEN_METADATA, false),
-            AccountMeta::new(get_metadata_pda(&mint.pubkey()), false),
-            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_metadata_pda(mint), false),
+            AccountMeta::new(*payer, true),
             AccountMeta::new_readonly(constants::accounts::SYSTEM_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::TOKEN_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::ASSOCIATED_TOKEN_PROGRAM, false),
@@ -128,7 +126,7 @@ pub fn create(payer: &Keypair, mint: &Keypair, args: Create) -> Instruction {
 ///
 /// # Arguments
 ///
-/// * `payer` - Keypair that will provide the SOL to buy tokens
+/// * `payer` - Public key of the account that will provide the SOL to buy tokens
 /// * `mint` - Public key of the token mint to buy
 /// * `fee_recipient` - Public key of the account that will receive the transaction fee
 /// * `args` - Buy instruction data containing the SOL amount and maximum acceptable token price
@@ -137,7 +135,7 @@ pub fn create(payer: &Keypair, mint: &Keypair, args: Create) -> Instruction {
 ///
 /// Returns a Solana instruction that when executed will buy tokens from the bonding curve
 pub fn buy(
-    payer: &Keypair,
+    payer: &Pubkey,
     mint: &Pubkey,
     fee_recipient: &Pubkey,
     args: Buy,
@@ -152,8 +150,8 @@ pub fn buy(
             AccountMeta::new_readonly(*mint, false),
             AccountMeta::new(bonding_curve, false),
             AccountMeta::new(get_associated_token_address(&bonding_curve, mint), false),
-            AccountMeta::new(get_associated_token_address(&payer.pubkey(), mint), false),
-            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_associated_token_address(payer, mint), false),
+            AccountMeta::new(*payer, true),
             AccountMeta::new_readonly(constants::accounts::SYSTEM_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::TOKEN_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::RENT, false),
@@ -171,7 +169,7 @@ pub fn buy(
 ///
 /// # Arguments
 ///
-/// * `payer` - Keypair that owns the tokens to sell
+/// * `payer` - Public key of the account that owns the tokens to sell
 /// * `mint` - Public key of the token mint to sell
 /// * `fee_recipient` - Public key of the account that will receive the transaction fee
 /// * `args` - Sell instruction data containing token amount and minimum acceptable SOL output
@@ -180,7 +178,7 @@ pub fn buy(
 ///
 /// Returns a Solana instruction that when executed will sell tokens to the bonding curve
 pub fn sell(
-    payer: &Keypair,
+    payer: &Pubkey,
     mint: &Pubkey,
     fee_recipient: &Pubkey,
     args: Sell,
@@ -195,8 +193,8 @@ pub fn sell(
             AccountMeta::new_readonly(*mint, false),
             AccountMeta::new(bonding_curve, false),
             AccountMeta::new(get_associated_token_address(&bonding_curve, mint), false),
-            AccountMeta::new(get_associated_token_address(&payer.pubkey(), mint), false),
-            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_associated_token_address(payer, mint), false),
+            AccountMeta::new(*payer, true),
             AccountMeta::new_readonly(constants::accounts::SYSTEM_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::ASSOCIATED_TOKEN_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::TOKEN_PROGRAM, false),
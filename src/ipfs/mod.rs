@@ -8,6 +8,8 @@ use reqwest::multipart::{Form, Part};
 use base64::{Engine as _, engine::general_purpose};
 use serde::{Deserialize, Serialize};
 
+use crate::error::{ClientError, ClientResult};
+
 /// Metadata structure for a token, matching the format expected by Pump.fun.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -63,7 +65,7 @@ pub struct CreateTokenMetadata {
     pub metadata_uri: Option<String>,
 }
 
-pub async fn create_token_metadata(metadata: CreateTokenMetadata, api_key: &str) -> Result<TokenMetadataIPFS, anyhow::Error> {
+pub async fn create_token_metadata(metadata: CreateTokenMetadata, api_key: &str) -> ClientResult<TokenMetadataIPFS> {
     let ipfs_url = if metadata.file.starts_with("http") || metadata.metadata_uri.is_some() {
         metadata.file
     } else {
@@ -83,10 +85,10 @@ pub async fn create_token_metadata(metadata: CreateTokenMetadata, api_key: &str)
         website: metadata.website,
     };
 
-    if metadata.metadata_uri.is_some() {
+    if let Some(metadata_uri) = metadata.metadata_uri {
         let token_metadata_ipfs = TokenMetadataIPFS {
             metadata: token_metadata,
-            metadata_uri: metadata.metadata_uri.unwrap(),
+            metadata_uri,
         };  
         Ok(token_metadata_ipfs)
     } else {
@@ -97,12 +99,13 @@ pub async fn create_token_metadata(metadata: CreateTokenMetadata, api_key: &str)
             .header("Authorization", format!("Bearer {}", api_key))
             .json(&token_metadata)
         .send()
-        .await?;
+        .await
+        .map_err(upload_error)?;
 
         // 确保请求成功
         if response.status().is_success() {
-            let res_data: serde_json::Value = response.json().await?;
-            let ipfs_hash = res_data["IpfsHash"].as_str().unwrap();
+            let res_data: serde_json::Value = response.json().await.map_err(upload_error)?;
+            let ipfs_hash = get_ipfs_hash(&res_data)?;
             let ipfs_url = format!("https://ipfs.io/ipfs/{}", ipfs_hash);
             let token_metadata_ipfs = TokenMetadataIPFS {
                 metadata: token_metadata,
@@ -111,23 +114,27 @@ pub async fn create_token_metadata(metadata: CreateTokenMetadata, api_key: &str)
             Ok(token_metadata_ipfs)
         } else {
             eprintln!("Error: {:?}", response.status());
-            Err(anyhow::anyhow!("Failed to create token metadata"))
+            Err(ClientError::UploadMetadataError(
+                format!("Failed to create token metadata: {}", response.status()).into(),
+            ))
         }
     }
 }
 
-pub async fn upload_base64_file(base64_string: &str, api_key: &str) -> Result<String, anyhow::Error> {
-    let decoded_bytes = general_purpose::STANDARD.decode(base64_string)?;
+pub async fn upload_base64_file(base64_string: &str, api_key: &str) -> ClientResult<String> {
+    let decoded_bytes = general_purpose::STANDARD.decode(base64_string).map_err(upload_error)?;
 
     let client = Client::builder()
         .timeout(Duration::from_secs(120))  // 增加超时时间到120秒
         .pool_max_idle_per_host(0) // 禁用连接池
         .pool_idle_timeout(None) // 禁用空闲超时
-        .build()?;
+        .build()
+        .map_err(upload_error)?;
 
     let part = Part::bytes(decoded_bytes)
         .file_name("file.png") // 添加文件扩展名
-        .mime_str("image/png")?; // 指定正确的MIME类型
+        .mime_str("image/png") // 指定正确的MIME类型
+        .map_err(upload_error)?;
 
     let form = Form::new().part("file", part);
 
@@ -137,25 +144,38 @@ pub async fn upload_base64_file(base64_string: &str, api_key: &str) -> Result<St
         .header("Accept", "application/json")
         .multipart(form)
         .send()
-        .await?;
+        .await
+        .map_err(upload_error)?;
 
     if response.status().is_success() {
-        let response_json: Value = response.json().await.map_err(|e| anyhow::anyhow!("Failed to parse JSON: {}", e))?;  
+        let response_json: Value = response.json().await.map_err(upload_error)?;  
         println!("{:#?}", response_json);
-        let ipfs_hash = response_json["IpfsHash"].as_str().unwrap();
+        let ipfs_hash = get_ipfs_hash(&response_json)?;
         let ipfs_url = format!("https://ipfs.io/ipfs/{}", ipfs_hash);
         Ok(ipfs_url)
     } else {
-        let error_text = response.text().await?;
+        let error_text = response.text().await.map_err(upload_error)?;
         eprintln!("Error: {:?}", error_text);
-        Err(anyhow::anyhow!("Failed to upload file to IPFS: {}", error_text))
+        Err(ClientError::UploadMetadataError(
+            format!("Failed to upload file to IPFS: {}", error_text).into(),
+        ))
     }
 }
 
-async fn file_to_base64(file_path: &str) -> Result<String, anyhow::Error> {
-    let mut file = File::open(file_path).await?;
+async fn file_to_base64(file_path: &str) -> ClientResult<String> {
+    let mut file = File::open(file_path).await.map_err(upload_error)?;
     let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).await?;
+    file.read_to_end(&mut buffer).await.map_err(upload_error)?;
     let base64_string = general_purpose::STANDARD.encode(&buffer);
     Ok(base64_string)
 }
+
+fn get_ipfs_hash(response: &Value) -> ClientResult<&str> {
+    response["IpfsHash"]
+        .as_str()
+        .ok_or_else(|| ClientError::UploadMetadataError("Missing IpfsHash in Pinata response".into()))
+}
+
+fn upload_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> ClientError {
+    ClientError::UploadMetadataError(Box::new(err))
+}
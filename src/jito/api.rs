@@ -1,10 +1,9 @@
-use std::env;
+use serde::Deserialize;
+use serde_json::Value;
+use solana_sdk::{clock::Slot, signature::Signature, transaction::TransactionError};
+use solana_transaction_status::TransactionConfirmationStatus;
 
-use anyhow::{Context, Result};
-use reqwest::Proxy;
-use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use std::convert::TryFrom;
+use crate::error::{ClientResult, TransactionFailure};
 
 #[derive(Debug)]
 pub struct TipAccountResult {
@@ -12,7 +11,249 @@ pub struct TipAccountResult {
 }
 
 impl TipAccountResult {
-    pub fn from(accounts: Vec<String>) -> Result<Self> {
+    pub fn from(accounts: Vec<String>) -> ClientResult<Self> {
         Ok(TipAccountResult { accounts })
     }
 }
+
+/// Status of a landed bundle, as returned by `getBundleStatuses`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BundleStatus {
+    pub bundle_id: String,
+    /// Signatures of the bundle's transactions, in bundle order
+    pub transactions: Vec<String>,
+    pub slot: Slot,
+    pub confirmation_status: Option<TransactionConfirmationStatus>,
+    pub err: Result<(), Value>,
+}
+
+/// State of a bundle submitted within the last five minutes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum InflightBundleState {
+    /// Bundle id not found in the last five minutes
+    Invalid,
+    /// Bundle has not failed, landed or been marked invalid yet
+    Pending,
+    /// All regions marked the bundle as failed and it was not forwarded
+    Failed,
+    /// Bundle landed on-chain
+    Landed,
+}
+
+/// Status of a recently submitted bundle, as returned by `getInflightBundleStatuses`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct InflightBundleStatus {
+    pub bundle_id: String,
+    pub status: InflightBundleState,
+    pub landed_slot: Option<Slot>,
+}
+
+/// A bundle that landed on-chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LandedBundle {
+    pub bundle_id: String,
+    pub slot: Slot,
+    /// Signatures of the bundle's transactions, in bundle order
+    pub signatures: Vec<Signature>,
+}
+
+/// Landed tip percentiles, in SOL, as returned by the tip floor API
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct TipFloor {
+    pub landed_tips_25th_percentile: f64,
+    pub landed_tips_50th_percentile: f64,
+    pub landed_tips_75th_percentile: f64,
+    pub landed_tips_95th_percentile: f64,
+    pub landed_tips_99th_percentile: f64,
+    pub ema_landed_tips_50th_percentile: f64,
+}
+
+/// Outcome of a whole bundle simulation
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BundleSimulationSummary {
+    Failed {
+        /// Bundle execution error, such as a transaction failure or exceeding the cost model
+        error: Value,
+        /// Signature of the failing transaction, if a transaction failed
+        tx_signature: Option<String>,
+    },
+    Succeeded,
+}
+
+/// Simulation result of one bundle transaction
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleTransactionSimulation {
+    pub err: Option<TransactionError>,
+    pub logs: Option<Vec<String>>,
+    pub units_consumed: Option<u64>,
+}
+
+impl BundleTransactionSimulation {
+    /// Returns the failure of the transaction, or None if it succeeded
+    pub fn failure(&self) -> Option<TransactionFailure> {
+        let error = self.err.clone()?;
+        Some(TransactionFailure::new(error, self.logs.clone().unwrap_or_default()))
+    }
+}
+
+/// Result of `simulateBundle`
+///
+/// Transactions run in bundle order and the simulation stops at the first failure, so there may
+/// be fewer transaction results than transactions.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleSimulation {
+    pub summary: BundleSimulationSummary,
+    pub transaction_results: Vec<BundleTransactionSimulation>,
+}
+
+/// Why a bundle failed simulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleSimulationFailure {
+    /// Index of the failing transaction in the bundle, None if the bundle failed as a whole
+    pub transaction_index: Option<usize>,
+    /// Error of the failing transaction, if the simulation returned one
+    pub failure: Option<TransactionFailure>,
+    /// Bundle execution error from the summary
+    pub error: Value,
+}
+
+impl BundleSimulation {
+    pub fn is_success(&self) -> bool {
+        matches!(self.summary, BundleSimulationSummary::Succeeded)
+    }
+
+    /// Returns why the bundle failed, or None if the simulation succeeded
+    ///
+    /// `signatures` are the signatures of the simulated transactions, in bundle order.
+    pub fn failure(&self, signatures: &[Signature]) -> Option<BundleSimulationFailure> {
+        let BundleSimulationSummary::Failed { error, tx_signature } = &self.summary else {
+            return None;
+        };
+
+        let transaction_index = tx_signature.as_ref()
+            .and_then(|tx_signature| signatures.iter().position(|signature| signature.to_string() == *tx_signature))
+            .or_else(|| self.transaction_results.iter().position(|result| result.err.is_some()));
+
+        Some(BundleSimulationFailure {
+            transaction_index,
+            failure: transaction_index
+                .and_then(|index| self.transaction_results.get(index))
+                .and_then(BundleTransactionSimulation::failure),
+            error: error.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_bundle_status() {
+        let value = json!({
+            "bundle_id": "892b79ed49138bfb3aa5441f0df6e06ef34f9ee8f3976c15b323605bae0cf51d",
+            "transactions": [
+                "3bC2M9fiACSjkTXZDgeNAuQ4ScTsdKGwR42ytFdhUvikqTmBheUxfsR1fDVsM5ADCMMspuwGkdm1uKbU246x5aE3",
+                "8t9hKYEYNbLvNqiSzP96S13XF1C2f1ro271Kdf7bkZ6EpjPLuDff1ywRy4gfaGSTubsM2FeYGDoT64ZwPm1cQUt"
+            ],
+            "slot": 242804011,
+            "confirmation_status": "finalized",
+            "err": { "Ok": null }
+        });
+        let status: BundleStatus = serde_json::from_value(value).unwrap();
+        assert_eq!(status.slot, 242804011);
+        assert_eq!(status.transactions.len(), 2);
+        assert_eq!(status.confirmation_status, Some(TransactionConfirmationStatus::Finalized));
+        assert!(status.err.is_ok());
+
+        let statuses: Vec<Option<BundleStatus>> = serde_json::from_value(json!([null])).unwrap();
+        assert_eq!(statuses, vec![None]);
+    }
+
+    #[test]
+    fn test_tip_floor() {
+        let value = json!([{
+            "time": "2024-09-01T12:58:00Z",
+            "landed_tips_25th_percentile": 6.001000000000001e-06,
+            "landed_tips_50th_percentile": 1e-05,
+            "landed_tips_75th_percentile": 3.6196500000000005e-05,
+            "landed_tips_95th_percentile": 0.0014479055000000002,
+            "landed_tips_99th_percentile": 0.010007999,
+            "ema_landed_tips_50th_percentile": 9.836078125000002e-06
+        }]);
+        let tip_floors: Vec<TipFloor> = serde_json::from_value(value).unwrap();
+        assert_eq!(tip_floors[0].landed_tips_50th_percentile, 1e-05);
+        assert_eq!(tip_floors[0].landed_tips_99th_percentile, 0.010007999);
+    }
+
+    #[test]
+    fn test_bundle_simulation() {
+        let value = json!({
+            "summary": "succeeded",
+            "transactionResults": [
+                { "err": null, "logs": ["Program 11111111111111111111111111111111 success"], "unitsConsumed": 150, "returnData": null },
+                { "err": null, "logs": [], "unitsConsumed": 300, "preExecutionAccounts": null, "postExecutionAccounts": null }
+            ]
+        });
+        let simulation: BundleSimulation = serde_json::from_value(value).unwrap();
+        assert!(simulation.is_success());
+        assert_eq!(simulation.transaction_results[1].units_consumed, Some(300));
+        assert!(simulation.failure(&[]).is_none());
+
+        let signatures: Vec<Signature> = (0..3).map(|_| Signature::new_unique()).collect();
+        let value = json!({
+            "summary": {
+                "failed": {
+                    "error": { "TransactionFailure": [[1, 2, 3], "Error processing Instruction 2: custom program error: 0x1772"] },
+                    "tx_signature": signatures[1].to_string()
+                }
+            },
+            "transactionResults": [
+                { "err": null, "logs": [], "unitsConsumed": 150 },
+                { "err": { "InstructionError": [2, { "Custom": 6002 }] }, "logs": null, "unitsConsumed": 2000 }
+            ]
+        });
+        let simulation: BundleSimulation = serde_json::from_value(value).unwrap();
+        assert!(!simulation.is_success());
+        let failure = simulation.failure(&signatures).unwrap();
+        assert_eq!(failure.transaction_index, Some(1));
+        let failure = failure.failure.unwrap();
+        assert_eq!(failure.instruction_index, Some(2));
+        assert!(failure.is_slippage());
+
+        let value = json!({
+            "summary": { "failed": { "error": "ExceedsCostModel", "tx_signature": null } },
+            "transactionResults": []
+        });
+        let simulation: BundleSimulation = serde_json::from_value(value).unwrap();
+        let failure = simulation.failure(&signatures).unwrap();
+        assert_eq!(failure.transaction_index, None);
+        assert_eq!(failure.error, json!("ExceedsCostModel"));
+    }
+
+    #[test]
+    fn test_inflight_bundle_status() {
+        let value = json!([
+            {
+                "bundle_id": "b31e5fae4923f345218403ac1ab242b46a72d4f2a38d131f474255ae88f1ec9a",
+                "status": "Invalid",
+                "landed_slot": null
+            },
+            {
+                "bundle_id": "e3c4d7933cf3210489b17307a14afbab2e4ae3c67c9e7157156f191f047aa6e8",
+                "status": "Landed",
+                "landed_slot": 280999028
+            }
+        ]);
+        let statuses: Vec<InflightBundleStatus> = serde_json::from_value(value).unwrap();
+        assert_eq!(statuses[0].status, InflightBundleState::Invalid);
+        assert_eq!(statuses[0].landed_slot, None);
+        assert_eq!(statuses[1].status, InflightBundleState::Landed);
+        assert_eq!(statuses[1].landed_slot, Some(280999028));
+    }
+}
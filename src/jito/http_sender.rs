@@ -23,11 +23,16 @@ use tokio::time::sleep;
 
 use crate::jito::{client_error::Result, request::RpcRequest, rpc_sender::RpcSender};
 
+/// Header carrying the Jito UUID
+const JITO_AUTH_HEADER: &str = "x-jito-auth";
+
 pub struct HttpSender {
     client: Arc<reqwest::Client>,
     url: String,
     request_id: AtomicU64,
     stats: RwLock<solana_rpc_client::rpc_sender::RpcTransportStats>,
+    /// Jito UUID of an authenticated rate limit tier
+    uuid: Option<String>,
 }
 
 /// Nonblocking [`RpcSender`] over HTTP.
@@ -57,6 +62,32 @@ impl HttpSender {
             url: url.to_string(),
             request_id: AtomicU64::new(0),
             stats: RwLock::new(solana_rpc_client::rpc_sender::RpcTransportStats::default()),
+            uuid: None,
+        }
+    }
+
+    /// Create an HTTP RPC sender authenticated with a Jito UUID.
+    ///
+    /// The UUID is sent with every request in the `x-jito-auth` header and the `uuid` query
+    /// parameter.
+    pub fn new_with_uuid<U: ToString>(url: U, uuid: Option<String>) -> Self {
+        Self {
+            uuid,
+            ..Self::new(url)
+        }
+    }
+
+    fn build_request(&self, request_json: String) -> reqwest::RequestBuilder {
+        let request = self.client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(request_json);
+
+        match &self.uuid {
+            Some(uuid) => request
+                .header(JITO_AUTH_HEADER, uuid)
+                .query(&[("uuid", uuid)]),
+            None => request,
         }
     }
 }
@@ -108,16 +139,7 @@ impl RpcSender for HttpSender {
 
         let mut too_many_requests_retries = 5;
         loop {
-            let response = {
-                let client = self.client.clone();
-                let request_json = request_json.clone();
-                client
-                    .post(&self.url)
-                    .header(CONTENT_TYPE, "application/json")
-                    .body(request_json)
-                    .send()
-                    .await
-            }?;
+            let response = self.build_request(request_json.clone()).send().await?;
 
             if !response.status().is_success() {
                 if response.status() == StatusCode::TOO_MANY_REQUESTS
@@ -208,6 +230,19 @@ mod tests {
             .await;
     }
 
+    #[test]
+    fn http_sender_sets_uuid() {
+        let http_sender = HttpSender::new("http://localhost:1234/api/v1/bundles");
+        let request = http_sender.build_request(String::new()).build().unwrap();
+        assert_eq!(request.url().as_str(), "http://localhost:1234/api/v1/bundles");
+        assert!(request.headers().get(JITO_AUTH_HEADER).is_none());
+
+        let http_sender = HttpSender::new_with_uuid("http://localhost:1234/api/v1/bundles", Some("abc-123".to_string()));
+        let request = http_sender.build_request(String::new()).build().unwrap();
+        assert_eq!(request.url().as_str(), "http://localhost:1234/api/v1/bundles?uuid=abc-123");
+        assert_eq!(request.headers()[JITO_AUTH_HEADER], "abc-123");
+    }
+
     #[tokio::test(flavor = "current_thread")]
     async fn http_sender_on_tokio_current_thread() {
         let http_sender = HttpSender::new("http://localhost:1234".to_string());
@@ -1,62 +1,238 @@
-use std::str::FromStr;
+use std::{future::Future, str::FromStr, sync::Arc, time::Duration};
 
-use anyhow::{anyhow, Result};
-use api::TipAccountResult;
+use api::{BundleSimulation, BundleStatus, InflightBundleState, InflightBundleStatus, LandedBundle, TipAccountResult};
+use futures::{stream::FuturesUnordered, StreamExt};
 use rand::seq::IteratorRandom;
-use solana_sdk::{
-    pubkey::Pubkey,
-    transaction::{Transaction, VersionedTransaction},
-};
-use tokio::sync::RwLock;
-use tracing::error;
+use solana_rpc_client::rpc_client::SerializableTransaction;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use tokio::{sync::RwLock, time::Instant};
+use tracing::{error, warn};
 
 pub mod api;
 pub mod client_error;
 pub mod http_sender;
+pub mod region;
 pub mod request;
 pub mod rpc_client;
 pub mod rpc_sender;
+pub mod tip;
 
+use crate::constants::jito::{BUNDLE_STATUS_POLL_INTERVAL, REGION_REQUEST_INTERVAL};
+use crate::error::{ClientError, ClientResult};
+use crate::jito::region::{JitoRegion, JitoRegionStats};
 use crate::jito::rpc_client::RpcClient;
+use crate::jito::tip::{JitoTipFloorSource, TipFloorSource, TipStrategy};
 
+/// How `JitoClient` submits a single transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JitoSubmission {
+    /// As a bundle of one transaction through `sendBundle`, returns the bundle id
+    #[default]
+    Bundle,
+    /// Through `sendTransaction` with `bundleOnly` revert protection, returns the signature
+    ///
+    /// The block engine forwards the transaction only as a bundle, so it cannot land reverted or
+    /// be sandwiched, and no bundle auction minimum applies beyond the transaction's own tip.
+    ProtectedTransaction,
+}
+
+/// Jito block engine client
+///
+/// Clones share the regions, their request budgets and the cached tip accounts.
+#[derive(Clone)]
 pub struct JitoClient {
     base_url: String,
-    tip_accounts: RwLock<Vec<String>>,
-    client: RpcClient,
+    tip_accounts: Arc<RwLock<Vec<String>>>,
+    regions: Vec<Arc<JitoRegion>>,
+    tip_floor_source: Arc<dyn TipFloorSource>,
+    submission: JitoSubmission,
+    /// RPC node with the Jito-Solana extension, for `simulateBundle`
+    simulation_client: Option<Arc<RpcClient>>,
 }
 
-impl Clone for JitoClient {
-    fn clone(&self) -> Self {
+impl JitoClient {
+    /// Creates a client for one block engine, `uuid` authenticates it for a higher rate limit
+    pub fn new(jito_url: &str, uuid: Option<String>) -> Self {
         Self {
-            base_url: self.base_url.clone(),
-            tip_accounts: RwLock::new(Vec::new()),
-            client: RpcClient::new(self.base_url.clone()),
+            base_url: jito_url.to_string(),
+            tip_accounts: Arc::new(RwLock::new(vec![])),
+            regions: vec![Arc::new(JitoRegion::new(jito_url.to_string(), uuid, REGION_REQUEST_INTERVAL))],
+            tip_floor_source: Arc::new(JitoTipFloorSource::default()),
+            submission: JitoSubmission::default(),
+            simulation_client: None,
         }
     }
-}
 
-impl JitoClient {
-    pub fn new(jito_url: &str, _uuid: Option<String>) -> Self {
-        Self {
-            base_url: jito_url.to_string(),
-            tip_accounts: RwLock::new(vec![]),
-            client: RpcClient::new(jito_url.to_string()),
+    /// Creates a client that submits every bundle to all the given block engine regions
+    ///
+    /// Requests to each region are spaced at least `request_interval` apart. Reads such as
+    /// bundle statuses go to the region that can take a request soonest. `uuid` authenticates
+    /// the requests to every region.
+    ///
+    /// Returns an error if there are no regions or a region url is not a valid url.
+    pub fn new_with_regions(
+        jito_urls: &[String],
+        uuid: Option<String>,
+        request_interval: Duration,
+    ) -> ClientResult<Self> {
+        let base_url = jito_urls.first()
+            .ok_or(ClientError::InvalidInput("At least one block engine url is required"))?
+            .clone();
+        if jito_urls.iter().any(|url| reqwest::Url::parse(url).is_err()) {
+            return Err(ClientError::InvalidInput("Invalid block engine url"));
+        }
+
+        Ok(Self {
+            base_url,
+            tip_accounts: Arc::new(RwLock::new(vec![])),
+            regions: jito_urls.iter()
+                .map(|url| Arc::new(JitoRegion::new(url.clone(), uuid.clone(), request_interval)))
+                .collect(),
+            tip_floor_source: Arc::new(JitoTipFloorSource::default()),
+            submission: JitoSubmission::default(),
+            simulation_client: None,
+        })
+    }
+
+    /// Replaces the Jito tip floor API as the source of landed tips for tip strategies
+    pub fn with_tip_floor_source(mut self, tip_floor_source: Arc<dyn TipFloorSource>) -> Self {
+        self.tip_floor_source = tip_floor_source;
+        self
+    }
+
+    /// Sets how `send_transaction` submits single transactions
+    pub fn with_submission(mut self, submission: JitoSubmission) -> Self {
+        self.submission = submission;
+        self
+    }
+
+    /// Simulates bundles on `rpc_url` before they are sent, which needs an RPC node running the
+    /// Jito-Solana extension
+    pub fn with_simulation_rpc(mut self, rpc_url: &str) -> Self {
+        self.simulation_client = Some(Arc::new(RpcClient::new(rpc_url.to_string())));
+        self
+    }
+
+    /// Returns true if bundles can be simulated, see `with_simulation_rpc`
+    pub fn can_simulate(&self) -> bool {
+        self.simulation_client.is_some()
+    }
+
+    /// Simulates the transactions as a bundle, with a result for each transaction that ran
+    pub async fn simulate_bundle(
+        &self,
+        transactions: &[impl SerializableTransaction],
+    ) -> ClientResult<BundleSimulation> {
+        let simulation_client = self.simulation_client.as_ref()
+            .ok_or(ClientError::InvalidInput("Bundle simulation needs a simulation RPC"))?;
+        Ok(simulation_client.simulate_bundle(transactions).await?.value)
+    }
+
+    /// Simulates the transactions as a bundle and fails if the simulation fails
+    pub async fn check_bundle_simulation(
+        &self,
+        transactions: &[impl SerializableTransaction],
+    ) -> ClientResult<()> {
+        let simulation = self.simulate_bundle(transactions).await?;
+        if simulation.is_success() {
+            return Ok(());
+        }
+
+        let signatures: Vec<Signature> = transactions.iter()
+            .map(|transaction| *transaction.get_signature())
+            .collect();
+        match simulation.failure(&signatures) {
+            Some(failure) => Err(ClientError::JitoBundleSimulationFailed(Box::new(failure))),
+            None => Ok(()),
         }
     }
 
-    pub async fn get_tip_accounts(&self) -> Result<TipAccountResult> {
-        let result = self.client.get_tip_accounts().await?;
-        TipAccountResult::from(result).map_err(|e| anyhow!(e))
+    /// Returns the tip in lamports for a tip strategy
+    pub async fn resolve_tip(&self, tip_strategy: TipStrategy) -> ClientResult<u64> {
+        tip_strategy.resolve(self.tip_floor_source.as_ref()).await
+    }
+
+    /// Returns the submission statistics of every region
+    pub fn region_stats(&self) -> Vec<JitoRegionStats> {
+        self.regions.iter().map(|region| region.stats()).collect()
+    }
+
+    /// Returns the region that can take a request soonest, after waiting for its request budget
+    async fn read_region(&self) -> &JitoRegion {
+        let region = self.regions.iter()
+            .min_by_key(|region| region.next_request())
+            .expect("at least one region");
+        region.throttle().await;
+        region
+    }
+
+    /// Submits a bundle to every region at once and returns the first accepted bundle id
+    async fn submit_bundle(
+        &self,
+        transactions: &[impl SerializableTransaction],
+    ) -> ClientResult<String> {
+        let serialized_encoded = Arc::new(self.regions[0].client.encode_bundle(transactions).await?);
+        self.submit_to_regions(|region| {
+            let serialized_encoded = serialized_encoded.clone();
+            async move { region.send_encoded_bundle(&serialized_encoded).await }
+        }).await
+    }
+
+    /// Submits a transaction with revert protection to every region at once and returns the
+    /// signature from the first region that accepts it
+    async fn submit_protected_transaction(
+        &self,
+        transaction: &impl SerializableTransaction,
+    ) -> ClientResult<String> {
+        let serialized_encoded = Arc::new(self.regions[0].client.encode_transaction(transaction).await?);
+        self.submit_to_regions(|region| {
+            let serialized_encoded = serialized_encoded.clone();
+            async move { region.send_encoded_transaction(&serialized_encoded).await }
+        }).await
+    }
+
+    /// Runs a submission against every region at once and returns the first accepted result
+    ///
+    /// The submissions run in their own tasks, so regions that answer later still receive the
+    /// submission. If no region accepts it, the first error is returned.
+    async fn submit_to_regions<F, Fut>(&self, submit: F) -> ClientResult<String>
+    where
+        F: Fn(Arc<JitoRegion>) -> Fut,
+        Fut: Future<Output = client_error::Result<String>> + Send + 'static,
+    {
+        let mut submissions: FuturesUnordered<_> = self.regions.iter()
+            .map(|region| tokio::spawn(submit(region.clone())))
+            .collect();
+
+        let mut first_error = None;
+        while let Some(result) = submissions.next().await {
+            match result {
+                Ok(Ok(bundle_id)) => return Ok(bundle_id),
+                Ok(Err(err)) => {
+                    first_error.get_or_insert(ClientError::from(err));
+                }
+                Err(err) => {
+                    first_error.get_or_insert(ClientError::Jito(err.to_string(), self.base_url.clone()));
+                }
+            }
+        }
+
+        Err(first_error.unwrap_or_else(|| ClientError::Jito("No block engine regions".to_string(), self.base_url.clone())))
     }
 
-    pub async fn init_tip_accounts(&self) -> Result<()> {
+    pub async fn get_tip_accounts(&self) -> ClientResult<TipAccountResult> {
+        let result = self.read_region().await.client.get_tip_accounts().await?;
+        TipAccountResult::from(result)
+    }
+
+    pub async fn init_tip_accounts(&self) -> ClientResult<()> {
         let accounts = self.get_tip_accounts().await?;
         let mut tip_accounts = self.tip_accounts.write().await;
         *tip_accounts = accounts.accounts.iter().map(|a| a.to_string()).collect();
         Ok(())
     }
 
-    pub async fn get_tip_account(&self) -> Result<Pubkey> {
+    pub async fn get_tip_account(&self) -> ClientResult<Pubkey> {
         {
             let accounts = self.tip_accounts.read().await;
             if !accounts.is_empty() {
@@ -64,7 +240,7 @@ impl JitoClient {
                     return Pubkey::from_str(acc)
                         .map_err(|err| {
                             error!("jito: failed to parse Pubkey: {:?}", err);
-                            anyhow!("Invalid pubkey format")
+                            ClientError::from(err)
                         });
                 }
             }
@@ -76,30 +252,178 @@ impl JitoClient {
         accounts
             .iter()
             .choose(&mut rand::rng())
-            .ok_or_else(|| anyhow!("jito: no tip accounts available"))
+            .ok_or_else(|| ClientError::Jito("No tip accounts available".to_string(), self.base_url.clone()))
             .and_then(|acc| {
                 Pubkey::from_str(acc).map_err(|err| {
                     error!("jito: failed to parse Pubkey: {:?}", err);
-                    anyhow!("Invalid pubkey format")
+                    ClientError::from(err)
                 })
             })
     }
 
+    /// Submits a single transaction as set by `with_submission`, returns the bundle id or the
+    /// signature
     pub async fn send_transaction(
         &self,
-        transaction: &Transaction,
-    ) -> Result<String, anyhow::Error> {
-        let bundles = vec![VersionedTransaction::from(transaction.clone())];
-        Ok(self.client.send_bundle(&bundles).await?)
+        transaction: &impl SerializableTransaction,
+    ) -> ClientResult<String> {
+        self.send_transaction_with_submission(transaction, self.submission).await
+    }
+
+    /// Submits a single transaction as a bundle or with revert protection, returns the bundle id
+    /// or the signature
+    ///
+    /// With a simulation RPC, a bundle that fails simulation is not sent.
+    pub async fn send_transaction_with_submission(
+        &self,
+        transaction: &impl SerializableTransaction,
+        submission: JitoSubmission,
+    ) -> ClientResult<String> {
+        match submission {
+            JitoSubmission::Bundle => {
+                let transactions = std::slice::from_ref(transaction);
+                if self.can_simulate() {
+                    self.check_bundle_simulation(transactions).await?;
+                }
+                self.submit_bundle(transactions).await
+            }
+            JitoSubmission::ProtectedTransaction => self.submit_protected_transaction(transaction).await,
+        }
     }
 
     pub async fn send_transactions(
         &self,
-        transactions: &Vec<Transaction>,
-    ) -> Result<String, anyhow::Error> {
-        let bundles: Vec<VersionedTransaction> = transactions.iter()
-        .map(|t| VersionedTransaction::from(t.clone()))
-        .collect();  // 显式指定类型
-        Ok(self.client.send_bundle(&bundles).await?)
+        transactions: &[impl SerializableTransaction],
+    ) -> ClientResult<String> {
+        self.submit_bundle(transactions).await
+    }
+
+    pub async fn get_bundle_statuses(
+        &self,
+        bundle_ids: &[String],
+    ) -> ClientResult<Vec<Option<BundleStatus>>> {
+        Ok(self.read_region().await.client.get_bundle_statuses(bundle_ids).await?.value)
+    }
+
+    pub async fn get_inflight_bundle_statuses(
+        &self,
+        bundle_ids: &[String],
+    ) -> ClientResult<Vec<Option<InflightBundleStatus>>> {
+        Ok(self.read_region().await.client.get_inflight_bundle_statuses(bundle_ids).await?.value)
+    }
+
+    /// Sends the transactions as a bundle and waits until it lands, fails or times out
+    pub async fn send_and_confirm_bundle(
+        &self,
+        transactions: &[impl SerializableTransaction],
+        timeout: Duration,
+    ) -> ClientResult<LandedBundle> {
+        let bundle_id = self.submit_bundle(transactions).await?;
+        self.confirm_bundle(&bundle_id, timeout).await
+    }
+
+    /// Polls the status of a bundle until it lands, fails or times out
+    ///
+    /// A failed status request is retried on the next poll. If the last poll before the deadline
+    /// failed, its error is returned instead of the timeout.
+    pub async fn confirm_bundle(
+        &self,
+        bundle_id: &str,
+        timeout: Duration,
+    ) -> ClientResult<LandedBundle> {
+        let bundle_ids = [bundle_id.to_string()];
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let mut last_error = None;
+            let inflight = match self.get_inflight_bundle_statuses(&bundle_ids).await {
+                Ok(inflight) => inflight,
+                Err(err) => {
+                    warn!("jito: failed to get the inflight status of bundle {bundle_id}: {err}");
+                    last_error = Some(err);
+                    vec![]
+                }
+            };
+            match inflight.into_iter().flatten().next().map(|status| status.status) {
+                Some(InflightBundleState::Failed) => {
+                    return Err(ClientError::JitoBundleFailed(bundle_id.to_string()));
+                }
+                Some(InflightBundleState::Landed) => {
+                    // The landed bundle can take a moment to show up in getBundleStatuses
+                    let statuses = match self.get_bundle_statuses(&bundle_ids).await {
+                        Ok(statuses) => statuses,
+                        Err(err) => {
+                            warn!("jito: failed to get the status of bundle {bundle_id}: {err}");
+                            last_error = Some(err);
+                            vec![]
+                        }
+                    };
+                    if let Some(status) = statuses.into_iter().flatten().next() {
+                        if status.err.is_err() {
+                            return Err(ClientError::JitoBundleFailed(bundle_id.to_string()));
+                        }
+
+                        let signatures = status.transactions.iter()
+                            .map(|signature| Signature::from_str(signature))
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|err| ClientError::Parse("Invalid bundle signature".to_string(), err.to_string()))?;
+
+                        return Ok(LandedBundle {
+                            bundle_id: status.bundle_id,
+                            slot: status.slot,
+                            signatures,
+                        });
+                    }
+                }
+                // A bundle reads as invalid until the block engine has seen it
+                Some(InflightBundleState::Pending | InflightBundleState::Invalid) | None => {}
+            }
+
+            if Instant::now() + BUNDLE_STATUS_POLL_INTERVAL > deadline {
+                return Err(last_error.unwrap_or_else(|| {
+                    ClientError::Timeout("Bundle was not confirmed in time".to_string(), bundle_id.to_string())
+                }));
+            }
+            tokio::time::sleep(BUNDLE_STATUS_POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_clone_shares_tip_accounts() {
+        let jito_client = JitoClient::new("http://127.0.0.1:1", Some("abc-123".to_string()));
+        let tip_account = Pubkey::new_unique();
+        jito_client.tip_accounts.write().await.push(tip_account.to_string());
+
+        let cloned = jito_client.clone();
+        assert_eq!(cloned.get_tip_account().await.unwrap(), tip_account);
+        assert!(Arc::ptr_eq(&cloned.regions[0], &jito_client.regions[0]));
+    }
+
+    #[test]
+    fn test_new_with_regions_rejects_invalid_urls() {
+        let interval = REGION_REQUEST_INTERVAL;
+        assert!(JitoClient::new_with_regions(&[], None, interval).is_err());
+        assert!(JitoClient::new_with_regions(&["ny.mainnet.block-engine.jito.wtf".to_string()], None, interval).is_err());
+
+        let jito_urls = vec!["https://ny.mainnet.block-engine.jito.wtf/api/v1/bundles".to_string()];
+        assert_eq!(JitoClient::new_with_regions(&jito_urls, None, interval).unwrap().regions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_bundle_retries_status_errors_until_deadline() {
+        let jito_client = JitoClient::new("http://127.0.0.1:1", None);
+        let timeout = REGION_REQUEST_INTERVAL + BUNDLE_STATUS_POLL_INTERVAL / 2;
+
+        let start = Instant::now();
+        let result = jito_client.confirm_bundle("bundle", timeout).await;
+
+        assert!(matches!(result, Err(ClientError::JitoClientError(_))));
+        assert!(start.elapsed() >= REGION_REQUEST_INTERVAL);
+        assert_eq!(jito_client.region_stats()[0].transport.request_count, 2);
     }
 }
@@ -0,0 +1,239 @@
+//! Block engine regions
+//!
+//! A `JitoClient` submits every bundle or protected transaction to each of its regions at once.
+//! Each region keeps its own request budget, so one busy region does not hold back the others, and
+//! its own submission metrics.
+
+use std::{
+    sync::{Mutex, RwLock},
+    time::Duration,
+};
+
+use solana_rpc_client::rpc_sender::RpcTransportStats;
+use tokio::time::Instant;
+
+use crate::jito::{client_error::Result, rpc_client::RpcClient};
+
+/// Submission statistics of one block engine region
+#[derive(Clone)]
+pub struct JitoRegionStats {
+    pub url: String,
+    /// Bundles sent to the region
+    pub bundles_sent: usize,
+    /// Bundles the region accepted
+    pub bundles_accepted: usize,
+    /// Single transactions sent to the region
+    pub transactions_sent: usize,
+    /// Single transactions the region accepted
+    pub transactions_accepted: usize,
+    /// Round trip of the last submission
+    pub last_latency: Option<Duration>,
+    /// Average round trip of the submissions
+    pub average_latency: Option<Duration>,
+    /// Time requests waited for the region's request budget
+    pub throttled_time: Duration,
+    pub transport: RpcTransportStats,
+}
+
+#[derive(Default)]
+struct SubmissionStats {
+    bundles_sent: usize,
+    bundles_accepted: usize,
+    transactions_sent: usize,
+    transactions_accepted: usize,
+    last_latency: Option<Duration>,
+    total_latency: Duration,
+    throttled_time: Duration,
+}
+
+/// Spaces requests to a region at least `interval` apart
+struct RateLimiter {
+    interval: Duration,
+    next_request: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_request: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Reserves the next free request slot and returns when it starts
+    fn reserve(&self) -> Instant {
+        let mut next_request = self.next_request.lock().unwrap();
+        let start = (*next_request).max(Instant::now());
+        *next_request = start + self.interval;
+        start
+    }
+
+    fn next_request(&self) -> Instant {
+        *self.next_request.lock().unwrap()
+    }
+}
+
+impl SubmissionStats {
+    fn record_latency(&mut self, latency: Duration) {
+        self.last_latency = Some(latency);
+        self.total_latency += latency;
+    }
+
+    fn submissions(&self) -> usize {
+        self.bundles_sent + self.transactions_sent
+    }
+}
+
+/// Returns the `sendTransaction` endpoint of the block engine serving `bundles_url`, with
+/// `bundleOnly` revert protection
+fn protected_transactions_url(bundles_url: &str) -> String {
+    let base_url = match bundles_url.trim_end_matches('/').strip_suffix("/bundles") {
+        Some(api_url) => api_url.to_string(),
+        None => format!("{}/api/v1", bundles_url.trim_end_matches('/')),
+    };
+    format!("{base_url}/transactions?bundleOnly=true")
+}
+
+/// One block engine region of a `JitoClient`
+pub(crate) struct JitoRegion {
+    pub(crate) client: RpcClient,
+    /// Client of the region's `sendTransaction` endpoint
+    transaction_client: RpcClient,
+    rate_limiter: RateLimiter,
+    stats: RwLock<SubmissionStats>,
+}
+
+impl JitoRegion {
+    pub(crate) fn new(url: String, uuid: Option<String>, request_interval: Duration) -> Self {
+        Self {
+            transaction_client: RpcClient::new_with_uuid(protected_transactions_url(&url), uuid.clone()),
+            client: RpcClient::new_with_uuid(url, uuid),
+            rate_limiter: RateLimiter::new(request_interval),
+            stats: RwLock::new(SubmissionStats::default()),
+        }
+    }
+
+    /// Waits until the region's request budget allows another request
+    pub(crate) async fn throttle(&self) {
+        let start = self.rate_limiter.reserve();
+        let wait = start.saturating_duration_since(Instant::now());
+        if !wait.is_zero() {
+            self.stats.write().unwrap().throttled_time += wait;
+            tokio::time::sleep_until(start).await;
+        }
+    }
+
+    /// When the region can take its next request without waiting
+    pub(crate) fn next_request(&self) -> Instant {
+        self.rate_limiter.next_request()
+    }
+
+    /// Sends an encoded bundle within the region's request budget and records the round trip
+    pub(crate) async fn send_encoded_bundle(&self, serialized_encoded: &[String]) -> Result<String> {
+        self.throttle().await;
+
+        let start = Instant::now();
+        let result = self.client.send_encoded_bundle(serialized_encoded).await;
+        let latency = start.elapsed();
+
+        let mut stats = self.stats.write().unwrap();
+        stats.bundles_sent += 1;
+        stats.record_latency(latency);
+        if result.is_ok() {
+            stats.bundles_accepted += 1;
+        }
+
+        result
+    }
+
+    /// Sends an encoded transaction with `bundleOnly` revert protection, within the region's
+    /// request budget, and records the round trip
+    pub(crate) async fn send_encoded_transaction(&self, serialized_encoded: &str) -> Result<String> {
+        self.throttle().await;
+
+        let start = Instant::now();
+        let result = self.transaction_client.send_encoded_transaction(serialized_encoded).await;
+        let latency = start.elapsed();
+
+        let mut stats = self.stats.write().unwrap();
+        stats.transactions_sent += 1;
+        stats.record_latency(latency);
+        if result.is_ok() {
+            stats.transactions_accepted += 1;
+        }
+
+        result
+    }
+
+    pub(crate) fn stats(&self) -> JitoRegionStats {
+        let stats = self.stats.read().unwrap();
+        let submissions = stats.submissions();
+        let bundle_transport = self.client.get_transport_stats();
+        let transaction_transport = self.transaction_client.get_transport_stats();
+
+        JitoRegionStats {
+            url: self.client.url(),
+            bundles_sent: stats.bundles_sent,
+            bundles_accepted: stats.bundles_accepted,
+            transactions_sent: stats.transactions_sent,
+            transactions_accepted: stats.transactions_accepted,
+            last_latency: stats.last_latency,
+            average_latency: (submissions > 0).then(|| stats.total_latency / submissions as u32),
+            throttled_time: stats.throttled_time,
+            transport: RpcTransportStats {
+                request_count: bundle_transport.request_count + transaction_transport.request_count,
+                elapsed_time: bundle_transport.elapsed_time + transaction_transport.elapsed_time,
+                rate_limited_time: bundle_transport.rate_limited_time + transaction_transport.rate_limited_time,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limiter_spaces_requests() {
+        let interval = Duration::from_millis(20);
+        let rate_limiter = RateLimiter::new(interval);
+
+        let first = rate_limiter.reserve();
+        assert_eq!(rate_limiter.reserve(), first + interval);
+        assert_eq!(rate_limiter.next_request(), first + interval * 2);
+
+        // A slot that has passed is not handed out again
+        tokio::time::sleep(interval * 3).await;
+        let now = Instant::now();
+        assert!(rate_limiter.reserve() >= now);
+    }
+
+    #[tokio::test]
+    async fn test_region_records_submissions() {
+        let region = JitoRegion::new("http://127.0.0.1:1".to_string(), None, Duration::from_millis(200));
+        assert!(region.stats().average_latency.is_none());
+
+        assert!(region.send_encoded_bundle(&[]).await.is_err());
+        assert!(region.send_encoded_bundle(&[]).await.is_err());
+
+        assert!(region.send_encoded_transaction("").await.is_err());
+
+        let stats = region.stats();
+        assert_eq!((stats.bundles_sent, stats.bundles_accepted), (2, 0));
+        assert_eq!((stats.transactions_sent, stats.transactions_accepted), (1, 0));
+        assert_eq!(stats.transport.request_count, 3);
+        assert!(stats.throttled_time > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_protected_transactions_url() {
+        assert_eq!(
+            protected_transactions_url("https://ny.mainnet.block-engine.jito.wtf/api/v1/bundles"),
+            "https://ny.mainnet.block-engine.jito.wtf/api/v1/transactions?bundleOnly=true"
+        );
+        assert_eq!(
+            protected_transactions_url("https://ny.mainnet.block-engine.jito.wtf/"),
+            "https://ny.mainnet.block-engine.jito.wtf/api/v1/transactions?bundleOnly=true"
+        );
+    }
+}
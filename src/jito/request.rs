@@ -6,8 +6,11 @@ use serde_json::{json, Value};
 pub enum RpcRequest {
     Custom { method: &'static str },
     GetBundlesStatuses,
+    GetInflightBundleStatuses,
     GetTipAccounts,
     SendBundle,
+    SendTransaction,
+    SimulateBundle,
 }
 
 impl fmt::Display for RpcRequest {
@@ -15,8 +18,11 @@ impl fmt::Display for RpcRequest {
         let method = match self {
             RpcRequest::Custom { method } => method,
             RpcRequest::GetBundlesStatuses => "getBundleStatuses",
+            RpcRequest::GetInflightBundleStatuses => "getInflightBundleStatuses",
             RpcRequest::GetTipAccounts => "getTipAccounts",
             RpcRequest::SendBundle => "sendBundle",
+            RpcRequest::SendTransaction => "sendTransaction",
+            RpcRequest::SimulateBundle => "simulateBundle",
         };
 
         write!(f, "{method}")
@@ -51,5 +57,15 @@ mod tests {
         let request = test_request.build_request_json(1, json!([addr]));
         assert_eq!(request["method"], "getBundleStatuses");
         assert_eq!(request["params"], json!([addr]));
+
+        let test_request = RpcRequest::GetInflightBundleStatuses;
+        let request = test_request.build_request_json(1, json!([addr]));
+        assert_eq!(request["method"], "getInflightBundleStatuses");
+        assert_eq!(request["params"], json!([addr]));
+
+        let test_request = RpcRequest::SendTransaction;
+        let request = test_request.build_request_json(1, json!([addr, {"encoding": "base64"}]));
+        assert_eq!(request["method"], "sendTransaction");
+        assert_eq!(request["params"], json!([addr, {"encoding": "base64"}]));
     }
 }
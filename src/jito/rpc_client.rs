@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use base64::prelude::{Engine, BASE64_STANDARD};
 use bincode::serialize;
 use log::*;
 use serde_json::{json, Value};
@@ -14,6 +15,7 @@ use solana_sdk::{bs58, commitment_config::CommitmentConfig};
 use solana_transaction_status::UiTransactionEncoding;
 
 use crate::jito::{
+    api::{BundleSimulation, BundleStatus, InflightBundleStatus},
     client_error,
     client_error::{Error as ClientError, Result as ClientResult},
     http_sender::HttpSender,
@@ -50,6 +52,14 @@ impl RpcClient {
         )
     }
 
+    /// Creates a client that authenticates every request with a Jito UUID
+    pub fn new_with_uuid(url: String, uuid: Option<String>) -> Self {
+        Self::new_sender(
+            HttpSender::new_with_uuid(url, uuid),
+            RpcClientConfig::with_commitment(CommitmentConfig::default()),
+        )
+    }
+
     pub fn new_with_timeout(url: String, timeout: Duration) -> Self {
         Self::new_sender(
             HttpSender::new_with_timeout(url, timeout),
@@ -69,11 +79,28 @@ impl RpcClient {
         &self,
         transactions: &[impl SerializableTransaction],
     ) -> ClientResult<String> {
+        let serialized_encoded = self.encode_bundle(transactions).await?;
+        self.send_encoded_bundle(&serialized_encoded).await
+    }
+
+    /// Serializes and encodes bundle transactions the way `send_bundle` sends them
+    pub async fn encode_bundle(
+        &self,
+        transactions: &[impl SerializableTransaction],
+    ) -> ClientResult<Vec<String>> {
         let mut serialized_encoded: Vec<String> = Vec::with_capacity(transactions.len());
         for transaction in transactions {
             let encoding = self.default_cluster_transaction_encoding().await?;
             serialized_encoded.push(serialize_and_encode(transaction, encoding)?);
         }
+        Ok(serialized_encoded)
+    }
+
+    /// Sends a bundle of transactions already encoded by `encode_bundle`
+    pub async fn send_encoded_bundle(
+        &self,
+        serialized_encoded: &[String],
+    ) -> ClientResult<String> {
         match self
             .send(RpcRequest::SendBundle, json!([serialized_encoded]))
             .await
@@ -91,6 +118,27 @@ impl RpcClient {
         }
     }
 
+    /// Serializes and encodes a transaction the way `send_encoded_transaction` sends it
+    pub async fn encode_transaction(
+        &self,
+        transaction: &impl SerializableTransaction,
+    ) -> ClientResult<String> {
+        serialize_and_encode(transaction, UiTransactionEncoding::Base64)
+    }
+
+    /// Sends a single transaction encoded by `encode_transaction` to the transactions endpoint
+    /// the client was created for, and returns its signature
+    pub async fn send_encoded_transaction(
+        &self,
+        serialized_encoded: &str,
+    ) -> ClientResult<String> {
+        self.send(
+            RpcRequest::SendTransaction,
+            json!([serialized_encoded, { "encoding": UiTransactionEncoding::Base64 }]),
+        )
+        .await
+    }
+
     async fn default_cluster_transaction_encoding(
         &self,
     ) -> Result<UiTransactionEncoding, RpcError> {
@@ -99,12 +147,50 @@ impl RpcClient {
 
     pub async fn get_bundle_statuses(
         &self,
-        signatures: &[String],
-    ) -> RpcResult<Vec<serde_json::Value>> {
-        self.send(RpcRequest::GetBundlesStatuses, json!([signatures]))
+        bundle_ids: &[String],
+    ) -> RpcResult<Vec<Option<BundleStatus>>> {
+        self.send(RpcRequest::GetBundlesStatuses, json!([bundle_ids]))
             .await
     }
 
+    pub async fn get_inflight_bundle_statuses(
+        &self,
+        bundle_ids: &[String],
+    ) -> RpcResult<Vec<Option<InflightBundleStatus>>> {
+        self.send(RpcRequest::GetInflightBundleStatuses, json!([bundle_ids]))
+            .await
+    }
+
+    /// Simulates the transactions as a bundle, in order and on top of each other
+    ///
+    /// Only RPC nodes running the Jito-Solana extension serve `simulateBundle`, block engines do
+    /// not.
+    pub async fn simulate_bundle(
+        &self,
+        transactions: &[impl SerializableTransaction],
+    ) -> RpcResult<BundleSimulation> {
+        let mut serialized_encoded: Vec<String> = Vec::with_capacity(transactions.len());
+        for transaction in transactions {
+            serialized_encoded.push(serialize_and_encode(transaction, UiTransactionEncoding::Base64)?);
+        }
+        let accounts_configs = vec![Value::Null; transactions.len()];
+
+        self.send(
+            RpcRequest::SimulateBundle,
+            json!([
+                { "encodedTransactions": serialized_encoded },
+                {
+                    "preExecutionAccountsConfigs": accounts_configs,
+                    "postExecutionAccountsConfigs": accounts_configs,
+                    "skipSigVerify": true,
+                    "replaceRecentBlockhash": false,
+                    "transactionEncoding": UiTransactionEncoding::Base64,
+                }
+            ]),
+        )
+        .await
+    }
+
     pub async fn get_tip_accounts(&self) -> ClientResult<Vec<String>> {
         self.send(RpcRequest::GetTipAccounts, Value::Null).await
     }
@@ -137,9 +223,10 @@ where
         .map_err(|e| ClientErrorKind::Custom(format!("Serialization failed: {e}")))?;
     let encoded = match encoding {
         UiTransactionEncoding::Base58 => bs58::encode(serialized).into_string(),
+        UiTransactionEncoding::Base64 => BASE64_STANDARD.encode(serialized),
         _ => {
             return Err(ClientErrorKind::Custom(format!(
-                "unsupported encoding: {encoding}. Supported encodings: base58"
+                "unsupported encoding: {encoding}. Supported encodings: base58, base64"
             ))
             .into())
         }
@@ -198,4 +285,13 @@ mod rpc_client_tests {
         let response = rpc_client.get_bundle_statuses(&[bundle_id]).await;
         println!("{:?}", response);
     }
+
+    #[tokio::test]
+    pub async fn get_inflight_bundle_statuses() {
+        let rpc_client = RpcClient::new(SERVER_URL.to_owned());
+        let bundle_id =
+            "6e4b90284778a40633b56e4289202ea79e62d2296bb3d45398bb93f6c9ec083d".to_owned();
+        let response = rpc_client.get_inflight_bundle_statuses(&[bundle_id]).await;
+        println!("{:?}", response);
+    }
 }
@@ -0,0 +1,184 @@
+//! Jito tip sizing
+//!
+//! A `TipStrategy` decides how many lamports a bundle tips, and is resolved when the bundle is
+//! built. Strategies that follow recently landed tips read them from a `TipFloorSource`, by
+//! default the Jito tip floor API.
+
+use async_trait::async_trait;
+use solana_sdk::native_token::sol_to_lamports;
+use tokio::{sync::RwLock, time::Instant};
+
+use crate::{
+    constants::{
+        jito::{TIP_FLOOR_MAX_AGE, TIP_FLOOR_URL},
+        trade::JITO_TIP_AMOUNT,
+    },
+    error::{ClientError, ClientResult},
+    jito::api::TipFloor,
+};
+
+/// Percentile of recently landed tips
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TipPercentile {
+    P25,
+    P50,
+    P75,
+    P95,
+    P99,
+    /// Exponential moving average of the 50th percentile
+    EmaP50,
+}
+
+/// How many lamports a bundle tips
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TipStrategy {
+    /// A fixed tip in lamports
+    Fixed(u64),
+    /// A percentile of recently landed tips
+    Percentile(TipPercentile),
+    /// A percentile of recently landed tips times `multiplier`, at most `max_lamports`
+    CappedMultiplier {
+        percentile: TipPercentile,
+        multiplier: f64,
+        max_lamports: u64,
+    },
+}
+
+impl Default for TipStrategy {
+    fn default() -> Self {
+        Self::Fixed(sol_to_lamports(JITO_TIP_AMOUNT))
+    }
+}
+
+impl TipStrategy {
+    /// Returns the tip in lamports, reading landed tips from `source` if the strategy needs them
+    pub async fn resolve(&self, source: &dyn TipFloorSource) -> ClientResult<u64> {
+        match *self {
+            Self::Fixed(lamports) => Ok(lamports),
+            Self::Percentile(percentile) => Ok(source.tip_floor().await?.lamports(percentile)),
+            Self::CappedMultiplier { percentile, multiplier, max_lamports } => {
+                let lamports = source.tip_floor().await?.lamports(percentile) as f64 * multiplier;
+                Ok((lamports as u64).min(max_lamports))
+            }
+        }
+    }
+}
+
+impl TipFloor {
+    /// Returns a percentile of landed tips in lamports
+    pub fn lamports(&self, percentile: TipPercentile) -> u64 {
+        sol_to_lamports(match percentile {
+            TipPercentile::P25 => self.landed_tips_25th_percentile,
+            TipPercentile::P50 => self.landed_tips_50th_percentile,
+            TipPercentile::P75 => self.landed_tips_75th_percentile,
+            TipPercentile::P95 => self.landed_tips_95th_percentile,
+            TipPercentile::P99 => self.landed_tips_99th_percentile,
+            TipPercentile::EmaP50 => self.ema_landed_tips_50th_percentile,
+        })
+    }
+}
+
+/// Source of recently landed tip percentiles
+#[async_trait]
+pub trait TipFloorSource: Send + Sync {
+    async fn tip_floor(&self) -> ClientResult<TipFloor>;
+}
+
+/// A fixed tip floor, for tests or tips configured ahead of time
+#[async_trait]
+impl TipFloorSource for TipFloor {
+    async fn tip_floor(&self) -> ClientResult<TipFloor> {
+        Ok(*self)
+    }
+}
+
+/// Tip floor from the Jito API, fetched again once it is older than `TIP_FLOOR_MAX_AGE`
+pub struct JitoTipFloorSource {
+    client: reqwest::Client,
+    url: String,
+    cached: RwLock<Option<(Instant, TipFloor)>>,
+}
+
+impl Default for JitoTipFloorSource {
+    fn default() -> Self {
+        Self::new(TIP_FLOOR_URL.to_string())
+    }
+}
+
+impl JitoTipFloorSource {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            cached: RwLock::new(None),
+        }
+    }
+
+    async fn fetch(&self) -> ClientResult<TipFloor> {
+        let tip_floors: Vec<TipFloor> = self.client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| ClientError::Jito("Failed to fetch tip floor".to_string(), err.to_string()))?
+            .json()
+            .await
+            .map_err(|err| ClientError::Parse("Invalid tip floor".to_string(), err.to_string()))?;
+
+        tip_floors
+            .into_iter()
+            .next()
+            .ok_or_else(|| ClientError::Jito("Empty tip floor".to_string(), self.url.clone()))
+    }
+}
+
+#[async_trait]
+impl TipFloorSource for JitoTipFloorSource {
+    async fn tip_floor(&self) -> ClientResult<TipFloor> {
+        if let Some((fetched_at, tip_floor)) = *self.cached.read().await {
+            if fetched_at.elapsed() < TIP_FLOOR_MAX_AGE {
+                return Ok(tip_floor);
+            }
+        }
+
+        let tip_floor = self.fetch().await?;
+        *self.cached.write().await = Some((Instant::now(), tip_floor));
+        Ok(tip_floor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIP_FLOOR: TipFloor = TipFloor {
+        landed_tips_25th_percentile: 0.000005,
+        landed_tips_50th_percentile: 0.00001,
+        landed_tips_75th_percentile: 0.00004,
+        landed_tips_95th_percentile: 0.0015,
+        landed_tips_99th_percentile: 0.01,
+        ema_landed_tips_50th_percentile: 0.000012,
+    };
+
+    #[tokio::test]
+    async fn test_tip_strategy_resolve() {
+        assert_eq!(TipStrategy::Fixed(1_234).resolve(&TIP_FLOOR).await.unwrap(), 1_234);
+        assert_eq!(TipStrategy::default().resolve(&TIP_FLOOR).await.unwrap(), 100_000);
+        assert_eq!(TipStrategy::Percentile(TipPercentile::P75).resolve(&TIP_FLOOR).await.unwrap(), 40_000);
+        assert_eq!(TipStrategy::Percentile(TipPercentile::EmaP50).resolve(&TIP_FLOOR).await.unwrap(), 12_000);
+
+        let capped = TipStrategy::CappedMultiplier {
+            percentile: TipPercentile::P50,
+            multiplier: 2.5,
+            max_lamports: 1_000_000,
+        };
+        assert_eq!(capped.resolve(&TIP_FLOOR).await.unwrap(), 25_000);
+
+        let capped = TipStrategy::CappedMultiplier {
+            percentile: TipPercentile::P99,
+            multiplier: 2.0,
+            max_lamports: 1_000_000,
+        };
+        assert_eq!(capped.resolve(&TIP_FLOOR).await.unwrap(), 1_000_000);
+    }
+}
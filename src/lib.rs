@@ -8,68 +8,147 @@ pub mod common;
 pub mod ipfs;
 pub mod trade;
 
-use anyhow::anyhow;
-use solana_client::rpc_client::RpcClient;
+use std::sync::Arc;
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client::rpc_client::RpcClientConfig;
 use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount,
     commitment_config::CommitmentConfig,
     pubkey::Pubkey,
-    signature::{Keypair, Signature},
+    signer::Signer,
 };
 
 use common::{logs_data::TradeInfo, logs_events::PumpfunEvent, logs_subscribe};
 use common::logs_subscribe::SubscriptionHandle;
+use common::account_cache::AccountCache;
+use common::fee_estimator::FeeEstimator;
+use common::blockhash_cache::{BlockhashCache, LatestBlockhash};
+use common::rpc_pool::{EndpointStats, RpcPool};
 use ipfs::TokenMetadataIPFS;
 
-use crate::jito::JitoClient;
+use crate::error::{ClientError, ClientResult};
+use crate::constants::jito::REGION_REQUEST_INTERVAL;
+use crate::jito::{region::JitoRegionStats, tip::TipStrategy, JitoClient, JitoSubmission};
 use crate::trade::common::PriorityFee;
+use crate::trade::quote::Quote;
+use crate::trade::retry::{RetryPolicy, RetryReport};
+use crate::trade::submit::TransactionOutcome;
+use crate::trade::bundle::{BundleReport, TipDistribution};
 
-pub struct PumpFun {
-    pub rpc: RpcClient,
-    pub jito_client: Option<JitoClient>,
+/// Creates a Jito client submitting to every block engine in `jito_urls`, authenticated with
+/// `jito_uuid`, None if there are none
+fn new_jito_client(jito_urls: &[String], jito_uuid: Option<String>) -> ClientResult<Option<Arc<JitoClient>>> {
+    if jito_urls.is_empty() {
+        return Ok(None);
+    }
+
+    let jito_client = JitoClient::new_with_regions(jito_urls, jito_uuid, REGION_REQUEST_INTERVAL)?;
+    Ok(Some(Arc::new(jito_client)))
 }
 
-impl Clone for PumpFun {
-    fn clone(&self) -> Self {
-        Self {
-            rpc: RpcClient::new_with_commitment(
-                self.rpc.url().to_string(),
-                self.rpc.commitment()
-            ),
-            jito_client: self.jito_client.clone(),
-        }
-    }
+/// Pump.fun client, cheap to clone and shared between clones
+#[derive(Clone)]
+pub struct PumpFun {
+    pub rpc: Arc<RpcClient>,
+    pub jito_client: Option<Arc<JitoClient>>,
+    pub blockhash_cache: BlockhashCache,
+    pub account_cache: AccountCache,
+    pub fee_estimator: FeeEstimator,
+    /// Endpoint pool behind `rpc` when created with [`PumpFun::new_with_pool`]
+    pub rpc_pool: Option<RpcPool>,
 }
 
 impl PumpFun {
+    /// Creates a client, `jito_urls` are the block engine regions every bundle is submitted to,
+    /// empty without Jito, and `jito_uuid` authenticates the requests to them
+    ///
+    /// Returns an error if a block engine url is invalid.
     #[inline]
     pub fn new(
         rpc_url: String,
         commitment: Option<CommitmentConfig>,
-        jito_url: Option<String>,
-    ) -> Self {
-        let rpc = RpcClient::new_with_commitment(
+        jito_urls: Vec<String>,
+        jito_uuid: Option<String>,
+    ) -> ClientResult<Self> {
+        let commitment = commitment.unwrap_or(CommitmentConfig::processed());
+        let blockhash_cache = BlockhashCache::new(rpc_url.clone());
+        let account_cache = AccountCache::new(commitment);
+        let rpc = Arc::new(RpcClient::new_with_commitment(
             rpc_url,
-            commitment.unwrap_or(CommitmentConfig::processed())
-        );   
+            commitment
+        ));
+
+        let jito_client = new_jito_client(&jito_urls, jito_uuid)?;
+
+        Ok(Self {
+            rpc,
+            jito_client,
+            blockhash_cache,
+            account_cache,
+            fee_estimator: FeeEstimator::new(),
+            rpc_pool: None,
+        })
+    }
 
-        let jito_client = jito_url.map(|url| JitoClient::new(&url, None));
+    /// Creates a client that spreads its RPC requests over several endpoints
+    ///
+    /// Reads go to the fastest healthy endpoint and transactions are sent to all of them, see
+    /// [`RpcPool`]. The Jito arguments are the same as in [`PumpFun::new`].
+    pub fn new_with_pool(
+        rpc_urls: Vec<String>,
+        commitment: Option<CommitmentConfig>,
+        jito_urls: Vec<String>,
+        jito_uuid: Option<String>,
+    ) -> ClientResult<Self> {
+        let commitment = commitment.unwrap_or(CommitmentConfig::processed());
+        let rpc_pool = RpcPool::new(rpc_urls)?;
+        let blockhash_cache = BlockhashCache::new_with_rpc(RpcClient::new_sender(
+            rpc_pool.clone(),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        ));
+        let account_cache = AccountCache::new(commitment);
+        let rpc = Arc::new(RpcClient::new_sender(
+            rpc_pool.clone(),
+            RpcClientConfig::with_commitment(commitment),
+        ));
 
-        Self {
+        let jito_client = new_jito_client(&jito_urls, jito_uuid)?;
+
+        Ok(Self {
             rpc,
             jito_client,
-        }
+            blockhash_cache,
+            account_cache,
+            fee_estimator: FeeEstimator::new(),
+            rpc_pool: Some(rpc_pool),
+        })
+    }
+
+    /// Returns the submission statistics of each Jito block engine region, empty without Jito
+    pub fn jito_region_stats(&self) -> Vec<JitoRegionStats> {
+        self.jito_client.as_ref()
+            .map(|jito_client| jito_client.region_stats())
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn jito_client(&self) -> ClientResult<&JitoClient> {
+        self.jito_client.as_deref()
+            .ok_or(ClientError::InvalidInput("Jito client not found"))
     }
 
     /// Create a new token
     pub async fn create(
         &self,
-        payer: &Keypair,
-        mint: &Keypair,
+        payer: &dyn Signer,
+        mint: &dyn Signer,
         ipfs: TokenMetadataIPFS,
         priority_fee: Option<PriorityFee>,
-    ) -> Result<Signature, anyhow::Error> {
+    ) -> ClientResult<TransactionOutcome> {
         trade::create::create(
             &self.rpc,
+            &self.blockhash_cache,
             payer,
             mint,
             ipfs,
@@ -79,15 +158,18 @@ impl PumpFun {
 
     pub async fn create_and_buy(
         &self,
-        payer: &Keypair,
-        mint: &Keypair,
+        payer: &dyn Signer,
+        mint: &dyn Signer,
         ipfs: TokenMetadataIPFS,
         amount_sol: u64,
         slippage_basis_points: Option<u64>,
         priority_fee: Option<PriorityFee>,
-    ) -> Result<Signature, anyhow::Error> {
+    ) -> ClientResult<TransactionOutcome> {
         trade::create::create_and_buy(
             &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            &self.fee_estimator,
             payer,
             mint,
             ipfs,
@@ -97,58 +179,70 @@ impl PumpFun {
         ).await
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_and_buy_list_with_jito(
         &self,
-        payers: Vec<&Keypair>,
-        mint: &Keypair,
+        payers: Vec<&dyn Signer>,
+        mint: &dyn Signer,
         ipfs: TokenMetadataIPFS,
         amount_sols: Vec<u64>,
         slippage_basis_points: Option<u64>,
-        jito_fee: Option<f64>,
-    ) -> Result<String, anyhow::Error> { 
+        tip_strategy: Option<TipStrategy>,
+        tip_distribution: TipDistribution<'_>,
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> ClientResult<Vec<BundleReport>> { 
         trade::create::create_and_buy_list_with_jito(
             &self.rpc,
-            &self.jito_client.as_ref().unwrap(),
+            &self.blockhash_cache,
+            &self.account_cache,
+            self.jito_client()?,
             payers,
             mint,
             ipfs,
             amount_sols,
             slippage_basis_points,
-            jito_fee,
+            tip_strategy,
+            tip_distribution,
+            lookup_tables,
         ).await
     }
 
     pub async fn create_and_buy_with_jito(
         &self,
-        payer: &Keypair,
-        mint: &Keypair,
+        payer: &dyn Signer,
+        mint: &dyn Signer,
         ipfs: TokenMetadataIPFS,
         amount_sol: u64,
         slippage_basis_points: Option<u64>,
-        jito_fee: Option<f64>,
-    ) -> Result<String, anyhow::Error> { 
+        tip_strategy: Option<TipStrategy>,
+    ) -> ClientResult<String> { 
         trade::create::create_and_buy_with_jito(
             &self.rpc,
-            &self.jito_client.as_ref().unwrap(),
+            &self.blockhash_cache,
+            &self.account_cache,
+            self.jito_client()?,
             payer,
             mint,
             ipfs,
             amount_sol,
             slippage_basis_points,
-            jito_fee,
+            tip_strategy,
         ).await
     }
     /// Buy tokens
     pub async fn buy(
         &self,
-        payer: &Keypair,
+        payer: &dyn Signer,
         mint: &Pubkey,
         amount_sol: u64,
         slippage_basis_points: Option<u64>,
         priority_fee: Option<PriorityFee>,
-    ) -> Result<Signature, anyhow::Error> {
+    ) -> ClientResult<TransactionOutcome> {
         trade::buy::buy(
             &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            &self.fee_estimator,
             payer,
             mint,
             amount_sol,
@@ -160,53 +254,159 @@ impl PumpFun {
     /// Buy tokens using Jito
     pub async fn buy_with_jito(
         &self,
-        payer: &Keypair,
+        payer: &dyn Signer,
         mint: &Pubkey,
         amount_sol: u64,
         slippage_basis_points: Option<u64>,
-        jito_fee: Option<f64>,
-    ) -> Result<String, anyhow::Error> {
+        tip_strategy: Option<TipStrategy>,
+    ) -> ClientResult<String> {
         trade::buy::buy_with_jito(
             &self.rpc,
-            &self.jito_client.as_ref().unwrap(),
+            &self.blockhash_cache,
+            &self.account_cache,
+            self.jito_client()?,
             payer,
             mint,
             amount_sol,
             slippage_basis_points,
-            jito_fee,
+            tip_strategy,
         ).await
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn buy_list_with_jito(
         &self,
-        payers: Vec<&Keypair>,
+        payers: Vec<&dyn Signer>,
         mint: &Pubkey,
         amount_sols: Vec<u64>,
         slippage_basis_points: Option<u64>,
-        jito_fee: Option<f64>,
-    ) -> Result<String, anyhow::Error> {
+        tip_strategy: Option<TipStrategy>,
+        tip_distribution: TipDistribution<'_>,
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> ClientResult<Vec<BundleReport>> {
         trade::buy::buy_list_with_jito(
             &self.rpc,
-            &self.jito_client.as_ref().unwrap(),
+            &self.blockhash_cache,
+            &self.account_cache,
+            self.jito_client()?,
             payers,
             mint,
             amount_sols,
             slippage_basis_points,
-            jito_fee,
+            tip_strategy,
+            tip_distribution,
+            lookup_tables,
+        ).await
+    }
+
+    /// Buy tokens spending at most `budget_sol`, the slippage lowers the token amount instead of
+    /// raising the SOL cost
+    pub async fn buy_with_budget(
+        &self,
+        payer: &dyn Signer,
+        mint: &Pubkey,
+        budget_sol: u64,
+        slippage_basis_points: Option<u64>,
+        priority_fee: Option<PriorityFee>,
+    ) -> ClientResult<TransactionOutcome> {
+        trade::buy::buy_with_budget(
+            &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            &self.fee_estimator,
+            payer,
+            mint,
+            budget_sol,
+            slippage_basis_points,
+            priority_fee,
+        ).await
+    }
+
+    /// Buy tokens, quoting and submitting again with a wider slippage while the buy fails its
+    /// slippage bound, as set by `policy`
+    pub async fn buy_with_retry(
+        &self,
+        payer: &dyn Signer,
+        mint: &Pubkey,
+        amount_sol: u64,
+        slippage_basis_points: Option<u64>,
+        priority_fee: Option<PriorityFee>,
+        policy: RetryPolicy,
+    ) -> RetryReport {
+        trade::retry::buy_with_retry(
+            &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            &self.fee_estimator,
+            payer,
+            mint,
+            amount_sol,
+            slippage_basis_points,
+            priority_fee,
+            policy,
+        ).await
+    }
+
+    /// Buy an exact amount of tokens
+    pub async fn buy_exact_tokens(
+        &self,
+        payer: &dyn Signer,
+        mint: &Pubkey,
+        amount_token: u64,
+        slippage_basis_points: Option<u64>,
+        priority_fee: Option<PriorityFee>,
+    ) -> ClientResult<TransactionOutcome> {
+        trade::buy::buy_exact_tokens(
+            &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            &self.fee_estimator,
+            payer,
+            mint,
+            amount_token,
+            slippage_basis_points,
+            priority_fee,
+        ).await
+    }
+
+    /// Buy an exact amount of tokens using Jito
+    pub async fn buy_exact_tokens_with_jito(
+        &self,
+        payer: &dyn Signer,
+        mint: &Pubkey,
+        amount_token: u64,
+        slippage_basis_points: Option<u64>,
+        tip_strategy: Option<TipStrategy>,
+    ) -> ClientResult<String> {
+        let jito_client = self.jito_client()?;
+
+        trade::buy::buy_exact_tokens_with_jito(
+            &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            jito_client,
+            payer,
+            mint,
+            amount_token,
+            slippage_basis_points,
+            tip_strategy,
         ).await
     }
 
     /// Sell tokens
     pub async fn sell(
         &self,
-        payer: &Keypair,
+        payer: &dyn Signer,
         mint: &Pubkey,
         amount_token: Option<u64>,
         slippage_basis_points: Option<u64>,
         priority_fee: Option<PriorityFee>,
-    ) -> Result<Signature, anyhow::Error> {
+    ) -> ClientResult<TransactionOutcome> {
         trade::sell::sell(
             &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            &self.fee_estimator,
             payer,
             mint,
             amount_token,
@@ -215,17 +415,45 @@ impl PumpFun {
         ).await
     }
 
+    /// Sell tokens, quoting and submitting again with a wider slippage while the sell fails its
+    /// slippage bound, as set by `policy`
+    pub async fn sell_with_retry(
+        &self,
+        payer: &dyn Signer,
+        mint: &Pubkey,
+        amount_token: Option<u64>,
+        slippage_basis_points: Option<u64>,
+        priority_fee: Option<PriorityFee>,
+        policy: RetryPolicy,
+    ) -> RetryReport {
+        trade::retry::sell_with_retry(
+            &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            &self.fee_estimator,
+            payer,
+            mint,
+            amount_token,
+            slippage_basis_points,
+            priority_fee,
+            policy,
+        ).await
+    }
+
     /// Sell tokens by percentage
     pub async fn sell_by_percent(
         &self,
-        payer: &Keypair,
+        payer: &dyn Signer,
         mint: &Pubkey,
         percent: u64,
         slippage_basis_points: Option<u64>,
         priority_fee: Option<PriorityFee>,
-    ) -> Result<Signature, anyhow::Error> {
+    ) -> ClientResult<TransactionOutcome> {
         trade::sell::sell_by_percent(
             &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            &self.fee_estimator,
             payer,
             mint,
             percent,
@@ -234,45 +462,96 @@ impl PumpFun {
         ).await
     }
 
+    /// Sell enough tokens to receive a target amount of SOL, net of fees
+    pub async fn sell_for_sol(
+        &self,
+        payer: &dyn Signer,
+        mint: &Pubkey,
+        amount_sol: u64,
+        slippage_basis_points: Option<u64>,
+        priority_fee: Option<PriorityFee>,
+    ) -> ClientResult<TransactionOutcome> {
+        trade::sell::sell_for_sol(
+            &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            &self.fee_estimator,
+            payer,
+            mint,
+            amount_sol,
+            slippage_basis_points,
+            priority_fee,
+        ).await
+    }
+
+    /// Sell enough tokens to receive a target amount of SOL using Jito
+    pub async fn sell_for_sol_with_jito(
+        &self,
+        payer: &dyn Signer,
+        mint: &Pubkey,
+        amount_sol: u64,
+        slippage_basis_points: Option<u64>,
+        tip_strategy: Option<TipStrategy>,
+    ) -> ClientResult<String> {
+        let jito_client = self.jito_client()?;
+
+        trade::sell::sell_for_sol_with_jito(
+            &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
+            payer,
+            jito_client,
+            mint,
+            amount_sol,
+            slippage_basis_points,
+            tip_strategy,
+        ).await
+    }
+
     pub async fn sell_by_percent_with_jito(
         &self,
-        payer: &Keypair,
+        payer: &dyn Signer,
         mint: &Pubkey,
         percent: u64,
         slippage_basis_points: Option<u64>,
-        jito_fee: Option<f64>,
-    ) -> Result<String, anyhow::Error> {
+        tip_strategy: Option<TipStrategy>,
+    ) -> ClientResult<String> {
         trade::sell::sell_by_percent_with_jito(
             &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
             payer,
-            self.jito_client.as_ref().unwrap(),
+            self.jito_client()?,
             mint,
             percent,
             slippage_basis_points,
-            jito_fee,
+            tip_strategy,
             ).await
     }
 
     /// Sell tokens using Jito
     pub async fn sell_with_jito(
         &self,
-        payer: &Keypair,
+        payer: &dyn Signer,
         mint: &Pubkey,
         amount_token: Option<u64>,
         slippage_basis_points: Option<u64>,
-        jito_fee: Option<f64>,
-    ) -> Result<String, anyhow::Error> {
-        let jito_client = self.jito_client.as_ref()
-            .ok_or_else(|| anyhow!("Jito client not found"))?;
+        tip_strategy: Option<TipStrategy>,
+        submission: Option<JitoSubmission>,
+    ) -> ClientResult<String> {
+        let jito_client = self.jito_client()?;
 
         trade::sell::sell_with_jito(
             &self.rpc,
+            &self.blockhash_cache,
+            &self.account_cache,
             payer,
             jito_client,
             mint,
             amount_token,
             slippage_basis_points,
-            jito_fee,
+            tip_strategy,
+            submission,
         ).await
     }
 
@@ -295,14 +574,44 @@ impl PumpFun {
         subscription_handle.shutdown().await;
     }
 
+    /// Keep the Global account cached from a websocket subscription
+    #[inline]
+    pub fn subscribe_global_account(&self, ws_url: &str) {
+        self.account_cache.subscribe_global_account(ws_url)
+    }
+
+    /// Keep the bonding curve of `mint` cached from a websocket subscription
     #[inline]
-    pub async fn get_sol_balance(&self, payer: &Pubkey) -> Result<u64, anyhow::Error> {
-        trade::common::get_sol_balance(&self.rpc, payer)
+    pub fn subscribe_bonding_curve(&self, ws_url: &str, mint: &Pubkey) -> ClientResult<()> {
+        self.account_cache.subscribe_bonding_curve(ws_url, mint)
     }
 
     #[inline]
-    pub async fn get_token_balance(&self, payer: &Pubkey, mint: &Pubkey) -> Result<u64, anyhow::Error> {
-        trade::common::get_token_balance(&self.rpc, payer, mint)
+    pub fn unsubscribe_bonding_curve(&self, mint: &Pubkey) {
+        self.account_cache.unsubscribe_bonding_curve(mint)
+    }
+
+    /// Returns the cached recent blockhash and its last valid block height
+    #[inline]
+    pub async fn get_latest_blockhash(&self) -> ClientResult<LatestBlockhash> {
+        self.blockhash_cache.get_latest_blockhash().await
+    }
+
+    /// Returns the health and transport statistics of each RPC endpoint, empty without a pool
+    pub fn rpc_endpoint_stats(&self) -> Vec<EndpointStats> {
+        self.rpc_pool.as_ref()
+            .map(RpcPool::endpoint_stats)
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    pub async fn get_sol_balance(&self, payer: &Pubkey) -> ClientResult<u64> {
+        trade::common::get_sol_balance(&self.rpc, payer).await
+    }
+
+    #[inline]
+    pub async fn get_token_balance(&self, payer: &Pubkey, mint: &Pubkey) -> ClientResult<u64> {
+        trade::common::get_token_balance(&self.rpc, payer, mint).await
     }
 
     #[inline]
@@ -315,8 +624,46 @@ impl PumpFun {
         trade::common::get_buy_price(amount, trade_info)
     }
 
+    /// Quotes a buy of `amount_sol` against the current bonding curve, without sending anything
+    pub async fn quote_buy(&self, mint: &Pubkey, amount_sol: u64, slippage_basis_points: Option<u64>) -> ClientResult<Quote> {
+        let global_account = self.account_cache.get_global_account(&self.rpc).await?;
+        let bonding_curve_account = self.account_cache.get_bonding_curve_account(&self.rpc, mint).await?;
+        trade::quote::quote_buy(&global_account, &bonding_curve_account, amount_sol, slippage_basis_points)
+    }
+
+    /// Quotes a buy spending at most `budget_sol` against the current bonding curve, without
+    /// sending anything
+    pub async fn quote_buy_with_budget(&self, mint: &Pubkey, budget_sol: u64, slippage_basis_points: Option<u64>) -> ClientResult<Quote> {
+        let global_account = self.account_cache.get_global_account(&self.rpc).await?;
+        let bonding_curve_account = self.account_cache.get_bonding_curve_account(&self.rpc, mint).await?;
+        trade::quote::quote_buy_with_budget(&global_account, &bonding_curve_account, budget_sol, slippage_basis_points)
+    }
+
+    /// Quotes a sell of `amount_token` against the current bonding curve, without sending anything
+    pub async fn quote_sell(&self, mint: &Pubkey, amount_token: u64, slippage_basis_points: Option<u64>) -> ClientResult<Quote> {
+        let global_account = self.account_cache.get_global_account(&self.rpc).await?;
+        let bonding_curve_account = self.account_cache.get_bonding_curve_account(&self.rpc, mint).await?;
+        trade::quote::quote_sell(&global_account, &bonding_curve_account, amount_token, slippage_basis_points)
+    }
+
     #[inline]
-    pub async fn transfer_sol(&self, payer: &Keypair, receive_wallet: &Pubkey, amount: u64) -> Result<(), anyhow::Error> {
+    pub async fn transfer_sol(&self, payer: &dyn Signer, receive_wallet: &Pubkey, amount: u64) -> ClientResult<()> {
         trade::common::transfer_sol(&self.rpc, payer, receive_wallet, amount).await
     }
+
+    /// Create an address lookup table holding the Pump.fun accounts
+    #[inline]
+    pub async fn create_pumpfun_lookup_table(&self, authority: &dyn Signer) -> ClientResult<AddressLookupTableAccount> {
+        trade::lookup_table::create_pumpfun_lookup_table(&self.rpc, authority).await
+    }
+
+    #[inline]
+    pub async fn extend_address_lookup_table(&self, authority: &dyn Signer, table: &Pubkey, addresses: &[Pubkey]) -> ClientResult<()> {
+        trade::lookup_table::extend_address_lookup_table(&self.rpc, authority, table, addresses).await
+    }
+
+    #[inline]
+    pub async fn get_address_lookup_table(&self, table: &Pubkey) -> ClientResult<AddressLookupTableAccount> {
+        trade::lookup_table::get_address_lookup_table(&self.rpc, table).await
+    }
 }
@@ -0,0 +1,666 @@
+//! Bundle planning for multi-wallet Jito trades
+//!
+//! The block engine rejects bundles with more than `MAX_BUNDLE_TRANSACTIONS` transactions. The
+//! planner packs the instructions of several wallets into one transaction while it fits in
+//! `MAX_TRANSACTION_SIZE`, and splits the transactions into ordered bundles. Bundles are sent one
+//! at a time and each one must land before the next is sent, so a create in the first bundle
+//! always lands before the buys that depend on it.
+//!
+//! Each bundle pays the Jito tip once, as set by its `TipDistribution`.
+//!
+//! When the Jito client has a simulation RPC, each bundle is simulated right before it is sent,
+//! once the bundles before it have landed.
+//!
+//! Each bundle is signed with a blockhash from the `BlockhashCache` right before it is sent, since
+//! waiting for the earlier bundles to land can outlast a blockhash fetched up front.
+
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount, hash::Hash, instruction::Instruction, message::{v0, Message, VersionedMessage}, pubkey::Pubkey, signature::Signature, signer::Signer, system_instruction, transaction::VersionedTransaction
+};
+
+use crate::{
+    common::blockhash_cache::BlockhashCache,
+    constants::{jito::{BUNDLE_CONFIRM_TIMEOUT, MAX_BUNDLE_TRANSACTIONS}, trade::MAX_TRANSACTION_SIZE},
+    error::{ClientError, ClientResult},
+    jito::{api::{BundleSimulationFailure, LandedBundle}, JitoClient},
+};
+
+use super::common::build_versioned_transaction;
+
+/// Instructions of one wallet in a multi-wallet trade
+#[derive(Clone)]
+pub struct BundleLeg<'a> {
+    /// Wallet that pays for and signs the instructions
+    pub payer: &'a dyn Signer,
+    /// Additional signers, such as the mint of a create
+    pub signers: Vec<&'a dyn Signer>,
+    pub instructions: Vec<Instruction>,
+}
+
+impl<'a> BundleLeg<'a> {
+    pub fn new(payer: &'a dyn Signer, instructions: Vec<Instruction>) -> Self {
+        Self { payer, signers: vec![], instructions }
+    }
+}
+
+/// Who pays the Jito tip of a bundle
+#[derive(Clone, Copy, Default)]
+pub enum TipDistribution<'a> {
+    /// The fee payer of the last transaction pays the whole tip
+    #[default]
+    LastTransaction,
+    /// The given wallet pays the whole tip in the last transaction and co-signs it
+    Payer(&'a dyn Signer),
+    /// Every wallet in the bundle pays an equal share of the tip in its own transaction
+    Split,
+}
+
+/// The Jito tip paid once per bundle
+#[derive(Clone, Copy)]
+pub struct BundleTip<'a> {
+    /// Tip account of the block engine
+    pub account: Pubkey,
+    /// Total tip of each bundle
+    pub lamports: u64,
+    pub distribution: TipDistribution<'a>,
+}
+
+impl BundleTip<'_> {
+    /// Returns the tip transfers a transaction may need, with placeholder amounts
+    ///
+    /// The size of a transfer does not depend on its amount, so these reserve room for the tip
+    /// before the transactions are assigned to bundles.
+    fn reserved_instructions(&self, fee_payer: &Pubkey, wallets: &[Pubkey]) -> Vec<Instruction> {
+        match self.distribution {
+            TipDistribution::LastTransaction => vec![self.transfer(fee_payer, self.lamports)],
+            TipDistribution::Payer(payer) => vec![self.transfer(&payer.pubkey(), self.lamports)],
+            TipDistribution::Split => wallets.iter().map(|wallet| self.transfer(wallet, self.lamports)).collect(),
+        }
+    }
+
+    fn transfer(&self, from: &Pubkey, lamports: u64) -> Instruction {
+        system_instruction::transfer(from, &self.account, lamports)
+    }
+}
+
+/// Signed transactions to be sent as one bundle
+#[derive(Debug, Clone)]
+pub struct PlannedBundle {
+    pub transactions: Vec<VersionedTransaction>,
+    /// Wallets with instructions in the bundle, in bundle order
+    pub payers: Vec<Pubkey>,
+}
+
+/// Outcome of sending one planned bundle, or of the wallets dropped from a bundle because their
+/// transaction failed simulation
+#[derive(Debug)]
+pub struct BundleReport {
+    /// Wallets with instructions in the bundle, in bundle order
+    pub payers: Vec<Pubkey>,
+    /// The landed bundle, or the reason it did not land
+    pub result: ClientResult<LandedBundle>,
+}
+
+impl BundleReport {
+    pub fn is_landed(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// A transaction being packed, paid for by the first wallet in it
+struct PackedTransaction<'a> {
+    instructions: Vec<Instruction>,
+    signers: Vec<&'a dyn Signer>,
+    payers: Vec<Pubkey>,
+}
+
+impl<'a> PackedTransaction<'a> {
+    fn new(leg: BundleLeg<'a>) -> Self {
+        let mut packed = Self { instructions: vec![], signers: vec![], payers: vec![] };
+        packed.push(leg);
+        packed
+    }
+
+    fn fee_payer(&self) -> Pubkey {
+        self.payers[0]
+    }
+
+    /// Returns each wallet of the transaction once
+    fn wallets(&self) -> Vec<Pubkey> {
+        let mut wallets: Vec<Pubkey> = vec![];
+        for payer in &self.payers {
+            if !wallets.contains(payer) {
+                wallets.push(*payer);
+            }
+        }
+        wallets
+    }
+
+    fn add_signer(&mut self, signer: &'a dyn Signer) {
+        if !self.signers.iter().any(|s| s.pubkey() == signer.pubkey()) {
+            self.signers.push(signer);
+        }
+    }
+
+    fn push(&mut self, leg: BundleLeg<'a>) {
+        self.payers.push(leg.payer.pubkey());
+        self.add_signer(leg.payer);
+        for signer in leg.signers {
+            self.add_signer(signer);
+        }
+        self.instructions.extend(leg.instructions);
+    }
+
+    /// Returns true if the transaction still fits with the leg and the reserved tip added
+    fn fits_with(&self, leg: &BundleLeg, tip: &BundleTip, lookup_tables: &[AddressLookupTableAccount]) -> bool {
+        let mut wallets = self.wallets();
+        if !wallets.contains(&leg.payer.pubkey()) {
+            wallets.push(leg.payer.pubkey());
+        }
+        let mut instructions: Vec<Instruction> = self.instructions.iter()
+            .chain(&leg.instructions)
+            .cloned()
+            .collect();
+        instructions.extend(tip.reserved_instructions(&self.fee_payer(), &wallets));
+        transaction_size(&instructions, &self.fee_payer(), lookup_tables) <= MAX_TRANSACTION_SIZE
+    }
+
+    fn sign(self, lookup_tables: &[AddressLookupTableAccount], recent_blockhash: Hash) -> ClientResult<VersionedTransaction> {
+        build_versioned_transaction(&self.fee_payer(), &self.instructions, &self.signers, lookup_tables, recent_blockhash)
+    }
+}
+
+/// Returns the serialized size of a signed transaction with the given instructions
+///
+/// The transaction is v0 if there are lookup tables, and legacy otherwise.
+pub fn transaction_size(instructions: &[Instruction], payer: &Pubkey, lookup_tables: &[AddressLookupTableAccount]) -> usize {
+    let message = if lookup_tables.is_empty() {
+        VersionedMessage::Legacy(Message::new(instructions, Some(payer)))
+    } else {
+        match v0::Message::try_compile(payer, instructions, lookup_tables, Hash::default()) {
+            Ok(message) => VersionedMessage::V0(message),
+            Err(_) => return usize::MAX,
+        }
+    };
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header().num_required_signatures as usize],
+        message,
+    };
+    bincode::serialized_size(&transaction).map_or(usize::MAX, |size| size as usize)
+}
+
+/// Packs the legs into transactions and splits them into bundles, keeping the order of the legs
+///
+/// Consecutive legs share a transaction while it fits in `MAX_TRANSACTION_SIZE`, paid for by the
+/// first wallet in it. Each bundle holds at most `MAX_BUNDLE_TRANSACTIONS` transactions and pays
+/// `tip` once. With lookup tables the transactions are v0 and load shared accounts from the
+/// tables, otherwise they are legacy.
+pub fn plan_bundles<'a>(
+    legs: Vec<BundleLeg<'a>>,
+    tip: &BundleTip<'a>,
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> ClientResult<Vec<PlannedBundle>> {
+    check_leg_sizes(&legs, tip, lookup_tables)?;
+
+    let mut bundles = vec![];
+    let mut planned = 0;
+    while let Some(bundle) = plan_next_bundle(&legs[planned..], tip, lookup_tables, recent_blockhash)? {
+        // A bundle holds one payer entry for each of its legs
+        planned += bundle.payers.len();
+        bundles.push(bundle);
+    }
+
+    Ok(bundles)
+}
+
+/// Returns an error if a leg does not fit in a transaction on its own, with its tip
+fn check_leg_sizes(legs: &[BundleLeg], tip: &BundleTip, lookup_tables: &[AddressLookupTableAccount]) -> ClientResult<()> {
+    for leg in legs {
+        let payer = leg.payer.pubkey();
+        let mut instructions = leg.instructions.clone();
+        instructions.extend(tip.reserved_instructions(&payer, &[payer]));
+        if transaction_size(&instructions, &payer, lookup_tables) > MAX_TRANSACTION_SIZE {
+            return Err(ClientError::InvalidInput("Instructions do not fit in a single transaction"));
+        }
+    }
+    Ok(())
+}
+
+/// Plans the first bundle of the legs, the same as the first bundle of `plan_bundles`, None if
+/// there are no legs
+///
+/// The bundle takes as many legs from the front as fit in `MAX_BUNDLE_TRANSACTIONS` transactions.
+fn plan_next_bundle<'a>(
+    legs: &[BundleLeg<'a>],
+    tip: &BundleTip<'a>,
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> ClientResult<Option<PlannedBundle>> {
+    let mut bundle: Vec<PackedTransaction> = vec![];
+    for leg in legs {
+        if let Some(transaction) = bundle.last_mut() {
+            if transaction.fits_with(leg, tip, lookup_tables) {
+                transaction.push(leg.clone());
+                continue;
+            }
+        }
+        if bundle.len() == MAX_BUNDLE_TRANSACTIONS {
+            break;
+        }
+        bundle.push(PackedTransaction::new(leg.clone()));
+    }
+
+    if bundle.is_empty() {
+        return Ok(None);
+    }
+    sign_bundle(bundle, tip, lookup_tables, recent_blockhash).map(Some)
+}
+
+/// Adds the tip to the bundle's transactions and signs them
+fn sign_bundle<'a>(
+    mut bundle: Vec<PackedTransaction<'a>>,
+    tip: &BundleTip<'a>,
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> ClientResult<PlannedBundle> {
+    let payers: Vec<Pubkey> = bundle.iter().flat_map(|t| t.payers.clone()).collect();
+
+    match tip.distribution {
+        TipDistribution::LastTransaction => {
+            if let Some(last) = bundle.last_mut() {
+                let fee_payer = last.fee_payer();
+                last.instructions.push(tip.transfer(&fee_payer, tip.lamports));
+            }
+        }
+        TipDistribution::Payer(payer) => {
+            if let Some(last) = bundle.last_mut() {
+                last.instructions.push(tip.transfer(&payer.pubkey(), tip.lamports));
+                last.add_signer(payer);
+            }
+        }
+        TipDistribution::Split => {
+            // A wallet pays its share once, in the first transaction it appears in
+            let mut wallets: Vec<Pubkey> = vec![];
+            let mut first_appearances: Vec<Vec<Pubkey>> = vec![];
+            for transaction in &bundle {
+                let new_wallets: Vec<Pubkey> = transaction.wallets().into_iter().filter(|w| !wallets.contains(w)).collect();
+                wallets.extend(&new_wallets);
+                first_appearances.push(new_wallets);
+            }
+
+            let share = tip.lamports / wallets.len() as u64;
+            let mut remainder = tip.lamports % wallets.len() as u64;
+            for (transaction, new_wallets) in bundle.iter_mut().zip(first_appearances) {
+                for wallet in new_wallets {
+                    let lamports = share + std::mem::take(&mut remainder);
+                    if lamports > 0 {
+                        transaction.instructions.push(tip.transfer(&wallet, lamports));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(PlannedBundle {
+        transactions: bundle.into_iter()
+            .map(|t| t.sign(lookup_tables, recent_blockhash))
+            .collect::<ClientResult<_>>()?,
+        payers,
+    })
+}
+
+/// Sends the bundles in order, waiting for each one to land before sending the next
+///
+/// A bundle that fails simulation is not sent. Sending stops at the first bundle that does not
+/// land, and the returned reports end with it.
+pub async fn send_bundles(jito_client: &JitoClient, bundles: Vec<PlannedBundle>) -> Vec<BundleReport> {
+    let mut reports = Vec::with_capacity(bundles.len());
+    for bundle in bundles {
+        let result = match simulate_bundle(jito_client, &bundle).await {
+            Ok(()) => jito_client.send_and_confirm_bundle(&bundle.transactions, BUNDLE_CONFIRM_TIMEOUT).await,
+            Err(err) => Err(err),
+        };
+        let landed = result.is_ok();
+        reports.push(BundleReport { payers: bundle.payers, result });
+        if !landed {
+            break;
+        }
+    }
+
+    reports
+}
+
+/// Plans the legs into bundles and sends them in order, like `plan_bundles` and `send_bundles`
+///
+/// Only the next bundle is planned and signed, with a blockhash from `blockhash_cache`, once the
+/// bundles before it have landed. If no blockhash can be fetched, sending stops and the legs left
+/// are reported with the error.
+///
+/// A bundle that fails simulation in a transaction of plain legs is repaired: the wallets of that
+/// transaction are dropped and reported with the failure, and the remaining legs are planned
+/// again. A failure in a transaction with extra signers, such as a create, or of the bundle as a
+/// whole rejects the bundle, and sending stops there.
+pub async fn plan_and_send_bundles<'a>(
+    jito_client: &JitoClient,
+    legs: Vec<BundleLeg<'a>>,
+    tip: &BundleTip<'a>,
+    lookup_tables: &[AddressLookupTableAccount],
+    blockhash_cache: &BlockhashCache,
+) -> ClientResult<Vec<BundleReport>> {
+    check_leg_sizes(&legs, tip, lookup_tables)?;
+
+    let mut remaining = legs;
+    let mut reports = vec![];
+    while !remaining.is_empty() {
+        let recent_blockhash = match blockhash_cache.get_blockhash().await {
+            Ok(recent_blockhash) => recent_blockhash,
+            Err(err) => {
+                let payers = remaining.iter().map(|leg| leg.payer.pubkey()).collect();
+                reports.push(BundleReport { payers, result: Err(err) });
+                break;
+            }
+        };
+        let Some(bundle) = plan_next_bundle(&remaining, tip, lookup_tables, recent_blockhash)? else {
+            break;
+        };
+        // A bundle holds one payer entry for each of its legs
+        let leg_count = bundle.payers.len();
+
+        if let Err(err) = simulate_bundle(jito_client, &bundle).await {
+            let failed_wallets = match &err {
+                ClientError::JitoBundleSimulationFailed(failure) => failed_wallets(failure, &bundle, &remaining[..leg_count]),
+                _ => None,
+            };
+
+            match failed_wallets {
+                Some(wallets) => {
+                    let mut index = 0;
+                    remaining.retain(|leg| {
+                        index += 1;
+                        index > leg_count || !wallets.contains(&leg.payer.pubkey())
+                    });
+                    reports.push(BundleReport { payers: wallets, result: Err(err) });
+                    continue;
+                }
+                None => {
+                    reports.push(BundleReport { payers: bundle.payers, result: Err(err) });
+                    break;
+                }
+            }
+        }
+
+        let result = jito_client.send_and_confirm_bundle(&bundle.transactions, BUNDLE_CONFIRM_TIMEOUT).await;
+        let landed = result.is_ok();
+        reports.push(BundleReport { payers: bundle.payers, result });
+        if !landed {
+            break;
+        }
+        remaining.drain(..leg_count);
+    }
+
+    Ok(reports)
+}
+
+/// Simulates the bundle if the Jito client can, and returns why it would fail
+async fn simulate_bundle(jito_client: &JitoClient, bundle: &PlannedBundle) -> ClientResult<()> {
+    if !jito_client.can_simulate() {
+        return Ok(());
+    }
+
+    jito_client.check_bundle_simulation(&bundle.transactions).await
+}
+
+/// Returns the wallets of the transaction that failed simulation, or None if dropping them
+/// cannot repair the bundle
+///
+/// `legs` are the legs planned into the bundle. Legs with extra signers are never dropped, since
+/// the legs after them may depend on them.
+fn failed_wallets(failure: &BundleSimulationFailure, bundle: &PlannedBundle, legs: &[BundleLeg]) -> Option<Vec<Pubkey>> {
+    let transaction = bundle.transactions.get(failure.transaction_index?)?;
+    let num_signers = transaction.message.header().num_required_signatures as usize;
+    let signers = &transaction.message.static_account_keys()[..num_signers];
+
+    let failed_legs: Vec<&BundleLeg> = legs.iter()
+        .filter(|leg| signers.contains(&leg.payer.pubkey()))
+        .collect();
+    if failed_legs.is_empty() || failed_legs.iter().any(|leg| !leg.signers.is_empty()) {
+        return None;
+    }
+
+    let mut wallets: Vec<Pubkey> = vec![];
+    for leg in failed_legs {
+        if !wallets.contains(&leg.payer.pubkey()) {
+            wallets.push(leg.payer.pubkey());
+        }
+    }
+    Some(wallets)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{instruction::AccountMeta, signature::Keypair, system_instruction::SystemInstruction, system_program};
+
+    use super::*;
+
+    fn transfers(payer: &Keypair, count: usize) -> Vec<Instruction> {
+        (0..count)
+            .map(|_| system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1))
+            .collect()
+    }
+
+    fn tip(distribution: TipDistribution) -> BundleTip {
+        BundleTip { account: Pubkey::new_unique(), lamports: 1_000_001, distribution }
+    }
+
+    fn verify(transaction: &VersionedTransaction) -> bool {
+        transaction.verify_with_results().iter().all(|verified| *verified)
+    }
+
+    /// Returns the payer and amount of every tip transfer in the transaction
+    fn tips(transaction: &VersionedTransaction, tip: &BundleTip) -> Vec<(Pubkey, u64)> {
+        let keys = transaction.message.static_account_keys();
+        transaction.message.instructions().iter()
+            .filter(|ix| keys[ix.program_id_index as usize] == system_program::id())
+            .filter(|ix| keys[ix.accounts[1] as usize] == tip.account)
+            .map(|ix| match bincode::deserialize(&ix.data).unwrap() {
+                SystemInstruction::Transfer { lamports } => (keys[ix.accounts[0] as usize], lamports),
+                _ => panic!("unexpected system instruction"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_bundles_packs_small_legs() {
+        let payers: Vec<Keypair> = (0..8).map(|_| Keypair::new()).collect();
+        let legs = payers.iter().map(|payer| BundleLeg::new(payer, transfers(payer, 1))).collect();
+
+        let bundles = plan_bundles(legs, &tip(TipDistribution::LastTransaction), &[], Hash::new_unique()).unwrap();
+        assert_eq!(bundles.len(), 1);
+        assert!(bundles[0].transactions.len() < payers.len());
+        assert_eq!(bundles[0].payers, payers.iter().map(|p| p.pubkey()).collect::<Vec<_>>());
+        for transaction in &bundles[0].transactions {
+            assert!(verify(transaction));
+            assert!(bincode::serialized_size(transaction).unwrap() as usize <= MAX_TRANSACTION_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_plan_bundles_splits_bundles() {
+        // Each leg fills most of a transaction, so no two legs share one
+        let payers: Vec<Keypair> = (0..12).map(|_| Keypair::new()).collect();
+        let legs = payers.iter().map(|payer| BundleLeg::new(payer, transfers(payer, 14))).collect();
+
+        let bundles = plan_bundles(legs, &tip(TipDistribution::LastTransaction), &[], Hash::new_unique()).unwrap();
+        assert_eq!(bundles.iter().map(|b| b.transactions.len()).collect::<Vec<_>>(), vec![5, 5, 2]);
+
+        let planned_payers: Vec<Pubkey> = bundles.iter().flat_map(|b| b.payers.clone()).collect();
+        assert_eq!(planned_payers, payers.iter().map(|p| p.pubkey()).collect::<Vec<_>>());
+        for (bundle, transaction) in bundles.iter().flat_map(|b| b.transactions.iter().map(move |t| (b, t))) {
+            assert!(verify(transaction));
+            assert!(bundle.payers.contains(&transaction.message.static_account_keys()[0]));
+        }
+    }
+
+    #[test]
+    fn test_plan_next_bundle() {
+        let payers: Vec<Keypair> = (0..12).map(|_| Keypair::new()).collect();
+        let legs: Vec<BundleLeg> = payers.iter().map(|payer| BundleLeg::new(payer, transfers(payer, 14))).collect();
+        let last = tip(TipDistribution::LastTransaction);
+        let recent_blockhash = Hash::new_unique();
+
+        // The next bundle is the first bundle of the whole plan, and takes one leg per transaction
+        let bundle = plan_next_bundle(&legs, &last, &[], recent_blockhash).unwrap().unwrap();
+        let first = plan_bundles(legs.clone(), &last, &[], recent_blockhash).unwrap().remove(0);
+        assert_eq!(bundle.payers, first.payers);
+        assert_eq!(bundle.transactions.iter().map(|t| t.signatures[0]).collect::<Vec<_>>(), first.transactions.iter().map(|t| t.signatures[0]).collect::<Vec<_>>());
+        assert_eq!(bundle.payers.len(), MAX_BUNDLE_TRANSACTIONS);
+
+        let rest = plan_next_bundle(&legs[10..], &last, &[], recent_blockhash).unwrap().unwrap();
+        assert_eq!(rest.payers, vec![payers[10].pubkey(), payers[11].pubkey()]);
+        assert!(plan_next_bundle(&[], &last, &[], recent_blockhash).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_plan_and_send_bundles_without_blockhash() {
+        let jito_client = JitoClient::new("http://127.0.0.1:1", None);
+        let blockhash_cache = BlockhashCache::new("http://127.0.0.1:1".to_string());
+        let payers: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+        let legs = payers.iter().map(|payer| BundleLeg::new(payer, transfers(payer, 1))).collect();
+
+        // Nothing is signed without a blockhash, and every wallet is reported with the error
+        let reports = plan_and_send_bundles(&jito_client, legs, &tip(TipDistribution::LastTransaction), &[], &blockhash_cache).await.unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].payers, payers.iter().map(|p| p.pubkey()).collect::<Vec<_>>());
+        assert!(!reports[0].is_landed());
+
+        // An oversized leg is rejected before anything is sent
+        let legs = vec![BundleLeg::new(&payers[0], transfers(&payers[0], 40))];
+        assert!(matches!(
+            plan_and_send_bundles(&jito_client, legs, &tip(TipDistribution::LastTransaction), &[], &blockhash_cache).await,
+            Err(ClientError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_plan_bundles_keeps_extra_signers_first() {
+        let creator = Keypair::new();
+        let mint = Keypair::new();
+        let buyer = Keypair::new();
+        let mut create_instructions = transfers(&creator, 1);
+        create_instructions.push(system_instruction::transfer(&mint.pubkey(), &creator.pubkey(), 1));
+        let legs = vec![
+            BundleLeg { payer: &creator, signers: vec![&mint], instructions: create_instructions },
+            BundleLeg::new(&buyer, transfers(&buyer, 1)),
+        ];
+
+        let bundles = plan_bundles(legs, &tip(TipDistribution::LastTransaction), &[], Hash::new_unique()).unwrap();
+        let transaction = &bundles[0].transactions[0];
+        assert_eq!(transaction.message.static_account_keys()[0], creator.pubkey());
+        assert!(verify(transaction));
+        assert_eq!(bundles[0].payers, vec![creator.pubkey(), buyer.pubkey()]);
+    }
+
+    #[test]
+    fn test_plan_bundles_rejects_oversized_leg() {
+        let payer = Keypair::new();
+        let legs = vec![BundleLeg::new(&payer, transfers(&payer, 40))];
+        assert!(matches!(
+            plan_bundles(legs, &tip(TipDistribution::LastTransaction), &[], Hash::new_unique()),
+            Err(ClientError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_tip_distribution() {
+        let payers: Vec<Keypair> = (0..7).map(|_| Keypair::new()).collect();
+        let tip_payer = Keypair::new();
+        let legs = || payers.iter().map(|payer| BundleLeg::new(payer, transfers(payer, 13))).collect();
+
+        // One tip per bundle, in the last transaction
+        let last = tip(TipDistribution::LastTransaction);
+        for bundle in plan_bundles(legs(), &last, &[], Hash::new_unique()).unwrap() {
+            let (last_transaction, others) = bundle.transactions.split_last().unwrap();
+            assert!(others.iter().all(|t| tips(t, &last).is_empty()));
+            assert_eq!(tips(last_transaction, &last), vec![(last_transaction.message.static_account_keys()[0], last.lamports)]);
+            assert!(verify(last_transaction));
+            assert!(bincode::serialized_size(last_transaction).unwrap() as usize <= MAX_TRANSACTION_SIZE);
+        }
+
+        // The designated wallet pays and co-signs
+        let payer = tip(TipDistribution::Payer(&tip_payer));
+        for bundle in plan_bundles(legs(), &payer, &[], Hash::new_unique()).unwrap() {
+            let all_tips: Vec<_> = bundle.transactions.iter().flat_map(|t| tips(t, &payer)).collect();
+            assert_eq!(all_tips, vec![(tip_payer.pubkey(), payer.lamports)]);
+            assert!(bundle.transactions.iter().all(verify));
+        }
+
+        // Every wallet pays a share, and the shares add up to one tip
+        let split = tip(TipDistribution::Split);
+        for bundle in plan_bundles(legs(), &split, &[], Hash::new_unique()).unwrap() {
+            let all_tips: Vec<_> = bundle.transactions.iter().flat_map(|t| tips(t, &split)).collect();
+            assert_eq!(all_tips.iter().map(|(wallet, _)| *wallet).collect::<Vec<_>>(), bundle.payers);
+            assert_eq!(all_tips.iter().map(|(_, lamports)| lamports).sum::<u64>(), split.lamports);
+            for transaction in &bundle.transactions {
+                assert!(verify(transaction));
+                assert!(bincode::serialized_size(transaction).unwrap() as usize <= MAX_TRANSACTION_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_wallets() {
+        let creator = Keypair::new();
+        let mint = Keypair::new();
+        let buyers: Vec<Keypair> = (0..2).map(|_| Keypair::new()).collect();
+        let mut create_instructions = transfers(&creator, 12);
+        create_instructions.push(system_instruction::transfer(&mint.pubkey(), &creator.pubkey(), 1));
+        let legs = vec![
+            BundleLeg { payer: &creator, signers: vec![&mint], instructions: create_instructions },
+            BundleLeg::new(&buyers[0], transfers(&buyers[0], 12)),
+            BundleLeg::new(&buyers[1], transfers(&buyers[1], 12)),
+        ];
+        let bundle = plan_bundles(legs.clone(), &tip(TipDistribution::LastTransaction), &[], Hash::new_unique()).unwrap().remove(0);
+        assert_eq!(bundle.transactions.len(), 3);
+
+        let failure = |transaction_index| BundleSimulationFailure {
+            transaction_index,
+            failure: None,
+            error: serde_json::Value::Null,
+        };
+        assert_eq!(failed_wallets(&failure(Some(1)), &bundle, &legs), Some(vec![buyers[0].pubkey()]));
+        assert_eq!(failed_wallets(&failure(Some(2)), &bundle, &legs), Some(vec![buyers[1].pubkey()]));
+        // The create is never dropped, and a failure of the whole bundle cannot be repaired
+        assert_eq!(failed_wallets(&failure(Some(0)), &bundle, &legs), None);
+        assert_eq!(failed_wallets(&failure(None), &bundle, &legs), None);
+        assert_eq!(failed_wallets(&failure(Some(3)), &bundle, &legs), None);
+    }
+
+    #[test]
+    fn test_plan_bundles_with_lookup_table() {
+        // Legs that share most of their accounts, like Pump.fun buys
+        let program_id = Pubkey::new_unique();
+        let shared: Vec<Pubkey> = (0..20).map(|_| Pubkey::new_unique()).collect();
+        let payers: Vec<Keypair> = (0..10).map(|_| Keypair::new()).collect();
+        let legs = || payers.iter()
+            .map(|payer| {
+                let mut accounts: Vec<AccountMeta> = shared.iter().map(|key| AccountMeta::new_readonly(*key, false)).collect();
+                accounts.push(AccountMeta::new(payer.pubkey(), true));
+                BundleLeg::new(payer, vec![Instruction::new_with_bytes(program_id, &[0; 24], accounts)])
+            })
+            .collect();
+        let lookup_table = AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: shared.clone() };
+        let last = tip(TipDistribution::LastTransaction);
+
+        let legacy = plan_bundles(legs(), &last, &[], Hash::new_unique()).unwrap();
+        let versioned = plan_bundles(legs(), &last, &[lookup_table], Hash::new_unique()).unwrap();
+        let count = |bundles: &[PlannedBundle]| bundles.iter().map(|b| b.transactions.len()).sum::<usize>();
+        assert!(count(&versioned) < count(&legacy));
+
+        for transaction in versioned.iter().flat_map(|b| &b.transactions) {
+            assert!(matches!(transaction.message, VersionedMessage::V0(_)));
+            assert!(verify(transaction));
+            assert!(bincode::serialized_size(transaction).unwrap() as usize <= MAX_TRANSACTION_SIZE);
+        }
+    }
+}
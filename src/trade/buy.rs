@@ -1,44 +1,72 @@
-use anyhow::anyhow;
-use solana_client::{rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction, instruction::Instruction, native_token::sol_to_lamports, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer, system_instruction, transaction::Transaction
-};
-use spl_associated_token_account::{
-    get_associated_token_address,
-    instruction::create_associated_token_account,
+    address_lookup_table::AddressLookupTableAccount, instruction::Instruction, pubkey::Pubkey, signer::Signer, transaction::{Transaction, VersionedTransaction}
 };
 use std::time::Instant;
+use tracing::debug;
 
-use crate::{constants::{self, trade::{DEFAULT_COMPUTE_UNIT_PRICE, DEFAULT_SLIPPAGE, JITO_TIP_AMOUNT}}, instruction, jito::JitoClient};
+use crate::{common::{account_cache::AccountCache, blockhash_cache::BlockhashCache, fee_estimator::FeeEstimator}, error::{ClientError, ClientResult}, jito::{tip::TipStrategy, JitoClient}};
 
-use super::common::{calculate_with_slippage_buy, get_bonding_curve_account, get_global_account, get_initial_buy_price, PriorityFee};
+use super::bundle::{plan_and_send_bundles, BundleLeg, BundleReport, BundleTip, TipDistribution};
+use super::common::{add_compute_budget_instructions, associated_token_account_exists, build_jito_tip_instruction, build_transaction, build_unsigned_transaction, build_versioned_transaction, PriorityFee};
+use super::offline::{self, TradeState};
+use super::submit::{submit_transaction, TransactionOutcome};
 
+/// Buy tokens, the transaction is rebroadcast until it lands or its blockhash expires
+#[allow(clippy::too_many_arguments)]
 pub async fn buy(
     rpc: &RpcClient,
-    payer: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
     mint: &Pubkey,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Signature, anyhow::Error> {
-    let transaction = build_buy_transaction(rpc, payer, mint, amount_sol, slippage_basis_points, priority_fee).await?;
-    let signature = rpc.send_transaction(&transaction)?;
-    Ok(signature)
+) -> ClientResult<TransactionOutcome> {
+    let instructions = build_buy_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), mint, amount_sol, slippage_basis_points, priority_fee).await?;
+    let latest = blockhash_cache.get_latest_blockhash().await?;
+    let transaction = build_transaction(&payer.pubkey(), &instructions, &[payer], latest.blockhash)?;
+    submit_transaction(rpc, &transaction, latest.last_valid_block_height, None).await
+}
+
+/// Buy tokens spending at most `budget_sol`, the slippage lowers the token amount instead of
+/// raising the SOL cost
+#[allow(clippy::too_many_arguments)]
+pub async fn buy_with_budget(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    budget_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<TransactionOutcome> {
+    let instructions = build_buy_with_budget_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), mint, budget_sol, slippage_basis_points, priority_fee).await?;
+    let latest = blockhash_cache.get_latest_blockhash().await?;
+    let transaction = build_transaction(&payer.pubkey(), &instructions, &[payer], latest.blockhash)?;
+    submit_transaction(rpc, &transaction, latest.last_valid_block_height, None).await
 }
 
 /// Buy tokens using Jito
+#[allow(clippy::too_many_arguments)]
 pub async fn buy_with_jito(
     rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
     jito_client: &JitoClient,
-    payer: &Keypair,
+    payer: &dyn Signer,
     mint: &Pubkey,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<String, anyhow::Error> {
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<String> {
     let start_time = Instant::now();
 
-    let transaction = build_buy_transaction_with_jito(rpc, jito_client, payer, mint, amount_sol, slippage_basis_points, jito_fee).await?;
+    let transaction = build_buy_transaction_with_jito(rpc, blockhash_cache, account_cache, jito_client, payer, mint, amount_sol, slippage_basis_points, tip_strategy).await?;
     let signature = jito_client.send_transaction(&transaction).await?;
 
     println!("Total Jito buy operation time: {:?}ms", start_time.elapsed().as_millis());
@@ -46,219 +74,378 @@ pub async fn buy_with_jito(
     Ok(signature)
 }
 
+/// Buy tokens from several wallets using Jito
+///
+/// The buys are packed into as few transactions as fit and split into bundles of at most
+/// `MAX_BUNDLE_TRANSACTIONS`. Each bundle pays the tip of `tip_strategy` once, as set by
+/// `tip_distribution`.
+/// With lookup tables the transactions are v0. Returns which wallets landed in which bundle.
+#[allow(clippy::too_many_arguments)]
 pub async fn buy_list_with_jito(
     rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
     jito_client: &JitoClient,
-    payers: Vec<&Keypair>,
+    payers: Vec<&dyn Signer>,
     mint: &Pubkey,
     amount_sols: Vec<u64>,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<String, anyhow::Error> {
+    tip_strategy: Option<TipStrategy>,
+    tip_distribution: TipDistribution<'_>,
+    lookup_tables: &[AddressLookupTableAccount],
+) -> ClientResult<Vec<BundleReport>> {
+    if payers.is_empty() || payers.len() != amount_sols.len() {
+        return Err(ClientError::InvalidInput("Each payer needs exactly one amount"));
+    }
+
     let start_time = Instant::now();
 
-    let mut transactions = vec![];
-    for (i, payer) in payers.iter().enumerate() {
-        let transaction = build_buy_transaction_with_jito(rpc, jito_client, payer, mint, amount_sols[i], slippage_basis_points, jito_fee).await?;
-        transactions.push(transaction);
+    let mut legs = vec![];
+    for (payer, amount_sol) in payers.into_iter().zip(amount_sols) {
+        let instructions = build_buy_bundle_instructions(rpc, account_cache, &payer.pubkey(), mint, amount_sol, slippage_basis_points).await?;
+        legs.push(BundleLeg::new(payer, instructions));
     }
-    
-    let signature = jito_client.send_transactions(&transactions).await?;
+
+    let tip = BundleTip {
+        account: jito_client.get_tip_account().await?,
+        lamports: jito_client.resolve_tip(tip_strategy.unwrap_or_default()).await?,
+        distribution: tip_distribution,
+    };
+    let reports = plan_and_send_bundles(jito_client, legs, &tip, lookup_tables, blockhash_cache).await?;
 
     println!("Total Jito buy operation time: {:?}ms", start_time.elapsed().as_millis());
 
+    Ok(reports)
+}
+
+/// Buy an exact amount of tokens, paying at most the quoted SOL cost plus slippage
+#[allow(clippy::too_many_arguments)]
+pub async fn buy_exact_tokens(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_token: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<TransactionOutcome> {
+    let instructions = build_buy_exact_tokens_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), mint, amount_token, slippage_basis_points, priority_fee).await?;
+    let latest = blockhash_cache.get_latest_blockhash().await?;
+    let transaction = build_transaction(&payer.pubkey(), &instructions, &[payer], latest.blockhash)?;
+    submit_transaction(rpc, &transaction, latest.last_valid_block_height, None).await
+}
+
+/// Buy an exact amount of tokens using Jito
+#[allow(clippy::too_many_arguments)]
+pub async fn buy_exact_tokens_with_jito(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    jito_client: &JitoClient,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_token: u64,
+    slippage_basis_points: Option<u64>,
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<String> {
+    let start_time = Instant::now();
+
+    let transaction = build_buy_exact_tokens_transaction_with_jito(rpc, blockhash_cache, account_cache, jito_client, payer, mint, amount_token, slippage_basis_points, tip_strategy).await?;
+    let signature = jito_client.send_transaction(&transaction).await?;
+
+    debug!("Total Jito buy operation time: {:?}ms", start_time.elapsed().as_millis());
+
     Ok(signature)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn build_buy_transaction(
     rpc: &RpcClient,
-    payer: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
     mint: &Pubkey,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Transaction, anyhow::Error> {
-    let instructions = build_buy_instructions(rpc, payer, mint, amount_sol, slippage_basis_points, priority_fee).await?;
-
-    let recent_blockhash = rpc.get_latest_blockhash()?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer],
-        recent_blockhash,
-    );
-
-    Ok(transaction)
+) -> ClientResult<Transaction> {
+    let instructions = build_buy_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), mint, amount_sol, slippage_basis_points, priority_fee).await?;
+
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_transaction(&payer.pubkey(), &instructions, &[payer], recent_blockhash)
 }
 
+/// Builds an unsigned buy transaction, for a separate signer to sign
+#[allow(clippy::too_many_arguments)]
+pub async fn build_buy_unsigned_transaction(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<Transaction> {
+    let instructions = build_buy_instructions(rpc, account_cache, fee_estimator, payer, mint, amount_sol, slippage_basis_points, priority_fee).await?;
+
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    Ok(build_unsigned_transaction(payer, &instructions, recent_blockhash))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn build_buy_transaction_with_jito(
     rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    jito_client: &JitoClient,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<Transaction> {
+    let instructions = build_buy_instructions_with_jito(rpc, account_cache, jito_client, &payer.pubkey(), mint, amount_sol, slippage_basis_points, tip_strategy).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_transaction(&payer.pubkey(), &instructions, &[payer], recent_blockhash)
+}
+
+/// Builds a v0 buy transaction that loads shared accounts from the lookup tables
+#[allow(clippy::too_many_arguments)]
+pub async fn build_buy_versioned_transaction(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+    lookup_tables: &[AddressLookupTableAccount],
+) -> ClientResult<VersionedTransaction> {
+    let instructions = build_buy_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), mint, amount_sol, slippage_basis_points, priority_fee).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_versioned_transaction(&payer.pubkey(), &instructions, &[payer], lookup_tables, recent_blockhash)
+}
+
+/// Builds a v0 Jito buy transaction that loads shared accounts from the lookup tables
+#[allow(clippy::too_many_arguments)]
+pub async fn build_buy_versioned_transaction_with_jito(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
     jito_client: &JitoClient,
-    payer: &Keypair,
+    payer: &dyn Signer,
     mint: &Pubkey,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<Transaction, anyhow::Error> {
-    let instructions = build_buy_instructions_with_jito(rpc, jito_client, payer, mint, amount_sol, slippage_basis_points, jito_fee).await?;
-    let recent_blockhash = rpc.get_latest_blockhash()?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer],
-        recent_blockhash,
-    );
-
-    Ok(transaction)
+    tip_strategy: Option<TipStrategy>,
+    lookup_tables: &[AddressLookupTableAccount],
+) -> ClientResult<VersionedTransaction> {
+    let instructions = build_buy_instructions_with_jito(rpc, account_cache, jito_client, &payer.pubkey(), mint, amount_sol, slippage_basis_points, tip_strategy).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_versioned_transaction(&payer.pubkey(), &instructions, &[payer], lookup_tables, recent_blockhash)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn build_buy_exact_tokens_transaction(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_token: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<Transaction> {
+    let instructions = build_buy_exact_tokens_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), mint, amount_token, slippage_basis_points, priority_fee).await?;
+
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_transaction(&payer.pubkey(), &instructions, &[payer], recent_blockhash)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn build_buy_exact_tokens_transaction_with_jito(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    jito_client: &JitoClient,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_token: u64,
+    slippage_basis_points: Option<u64>,
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<Transaction> {
+    let instructions = build_buy_exact_tokens_instructions_with_jito(rpc, account_cache, jito_client, &payer.pubkey(), mint, amount_token, slippage_basis_points, tip_strategy).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_transaction(&payer.pubkey(), &instructions, &[payer], recent_blockhash)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn build_buy_instructions(
     rpc: &RpcClient,
-    payer: &Keypair,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &Pubkey,
     mint: &Pubkey,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Vec<Instruction>, anyhow::Error> {
+) -> ClientResult<Vec<Instruction>> {
     if amount_sol == 0 {
-        return Err(anyhow!("Amount cannot be zero"));
-    }
-
-    let global_account = get_global_account(rpc).await?;
-    let bonding_curve_account = get_bonding_curve_account(rpc, mint).await?;
-    let buy_amount = bonding_curve_account
-        .get_buy_price(amount_sol)
-        .map_err(|e| anyhow!(e))?;
-    let buy_amount_with_slippage = calculate_with_slippage_buy(amount_sol, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE));
-
-    let mut instructions = vec![
-        ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
-        ComputeBudgetInstruction::set_compute_unit_price(0),
-    ];
-
-    let ata = get_associated_token_address(&payer.pubkey(), mint);
-    if rpc.get_account(&ata).is_err() {
-        instructions.push(create_associated_token_account(
-            &payer.pubkey(),
-            &payer.pubkey(),
-            mint,
-            &constants::accounts::TOKEN_PROGRAM,
-        ));
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
     }
 
-    instructions.push(instruction::buy(
-        payer,
-        mint,
-        &global_account.fee_recipient,
-        instruction::Buy {
-            _amount: buy_amount,
-            _max_sol_cost: buy_amount_with_slippage,
-        },
-    ));
-
-    let commitment_config = CommitmentConfig::confirmed();
-    let recent_blockhash = rpc.get_latest_blockhash_with_commitment(commitment_config)?
-        .0;
-
-    let simulate_tx = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer],
-        recent_blockhash,
-    );
-
-    let config = RpcSimulateTransactionConfig {
-        sig_verify: true,
-        commitment: Some(commitment_config),
-        ..RpcSimulateTransactionConfig::default()
+    let global_account = account_cache.get_global_account(rpc).await?;
+    let bonding_curve_account = account_cache.get_bonding_curve_account(rpc, mint).await?;
+    let state = TradeState {
+        global_account: &global_account,
+        bonding_curve_account: Some(&bonding_curve_account),
+        ata_exists: associated_token_account_exists(rpc, payer, mint).await,
     };
-    
-    let result = rpc.simulate_transaction_with_config(&simulate_tx, config)?
-        .value;
 
-    if result.logs.as_ref().map_or(true, |logs| logs.is_empty()) {
-        return Err(anyhow!("Simulation failed: {:?}", result.err));
-    }
+    let instructions = offline::build_buy_instructions(payer, mint, &state, amount_sol, slippage_basis_points)?;
+    add_compute_budget_instructions(rpc, fee_estimator, payer, mint, instructions, priority_fee).await
+}
 
-    let result_cu = result.units_consumed.ok_or_else(|| anyhow!("No compute units consumed"))?;
-    let fees = rpc.get_recent_prioritization_fees(&[])?;
-    let average_fees = if fees.is_empty() {
-        DEFAULT_COMPUTE_UNIT_PRICE
-    } else {
-        fees.iter()
-            .map(|fee| fee.prioritization_fee)
-            .sum::<u64>() / fees.len() as u64
-    };
+/// Builds buy instructions for an exact token amount
+///
+/// The maximum SOL cost is the fee-inclusive curve cost of `amount_token`, widened by the slippage.
+#[allow(clippy::too_many_arguments)]
+pub async fn build_buy_exact_tokens_instructions(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_token: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<Vec<Instruction>> {
+    let instructions = build_buy_exact_tokens_bundle_instructions(rpc, account_cache, payer, mint, amount_token, slippage_basis_points).await?;
+    add_compute_budget_instructions(rpc, fee_estimator, payer, mint, instructions, priority_fee).await
+}
 
-    let unit_price = match priority_fee {
-        None => average_fees,
-        Some(pf) => pf.price.unwrap_or(DEFAULT_COMPUTE_UNIT_PRICE)
+/// Builds buy instructions that spend at most `budget_sol`
+///
+/// The max SOL cost is the budget, and the token amount is the quote for the budget less the
+/// slippage.
+#[allow(clippy::too_many_arguments)]
+pub async fn build_buy_with_budget_instructions(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    budget_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<Vec<Instruction>> {
+    let global_account = account_cache.get_global_account(rpc).await?;
+    let bonding_curve_account = account_cache.get_bonding_curve_account(rpc, mint).await?;
+    let state = TradeState {
+        global_account: &global_account,
+        bonding_curve_account: Some(&bonding_curve_account),
+        ata_exists: associated_token_account_exists(rpc, payer, mint).await,
     };
 
-    let unit_price = if unit_price == 0 { DEFAULT_COMPUTE_UNIT_PRICE } else { unit_price };
+    let instructions = offline::build_buy_with_budget_instructions(payer, mint, &state, budget_sol, slippage_basis_points)?;
+    add_compute_budget_instructions(rpc, fee_estimator, payer, mint, instructions, priority_fee).await
+}
 
-    instructions[0] = ComputeBudgetInstruction::set_compute_unit_limit(result_cu as u32);
-    instructions[1] = ComputeBudgetInstruction::set_compute_unit_price(unit_price);
+#[allow(clippy::too_many_arguments)]
+pub async fn build_buy_instructions_with_jito(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    jito_client: &JitoClient,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<Vec<Instruction>> {
+    let mut instructions = build_buy_bundle_instructions(rpc, account_cache, payer, mint, amount_sol, slippage_basis_points).await?;
+    let tip_lamports = jito_client.resolve_tip(tip_strategy.unwrap_or_default()).await?;
+    instructions.push(build_jito_tip_instruction(jito_client, payer, tip_lamports).await?);
 
     Ok(instructions)
-
 }
 
-pub async fn build_buy_instructions_with_jito(
+/// Builds buy instructions for a bundle, without a Jito tip
+///
+/// The bundle planner adds the tip, see `trade::bundle::TipDistribution`.
+pub async fn build_buy_bundle_instructions(
     rpc: &RpcClient,
-    jito_client: &JitoClient,
-    payer: &Keypair,
+    account_cache: &AccountCache,
+    payer: &Pubkey,
     mint: &Pubkey,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<Vec<Instruction>, anyhow::Error> {
+) -> ClientResult<Vec<Instruction>> {
     if amount_sol == 0 {
-        return Err(anyhow!("Amount cannot be zero"));
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
     }
 
-    let global_account = get_global_account(rpc).await?;
-    let buy_amount = match get_bonding_curve_account(rpc, mint).await {
-        Ok(account) => account.get_buy_price(amount_sol).map_err(|e| anyhow!(e))?,
-        Err(_e) => {
-            let initial_buy_amount = get_initial_buy_price(&global_account, amount_sol).await?;
-            initial_buy_amount * 80 / 100
-        }
+    let global_account = account_cache.get_global_account(rpc).await?;
+    let bonding_curve_account = match account_cache.get_bonding_curve_account(rpc, mint).await {
+        Ok(account) => Some(account),
+        // The curve does not exist yet when the mint is created in the same bundle
+        Err(ClientError::BondingCurveNotFound) => None,
+        Err(e) => return Err(e),
     };
-    
-    let buy_amount_with_slippage = calculate_with_slippage_buy(amount_sol, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE));
-
-    let mut instructions = vec![];
-    let ata = get_associated_token_address(&payer.pubkey(), mint);
-    if rpc.get_account(&ata).is_err() {
-        instructions.push(create_associated_token_account(
-            &payer.pubkey(),
-            &payer.pubkey(),
-            mint,
-            &constants::accounts::TOKEN_PROGRAM,
-        ));
-    }
+    let state = TradeState {
+        global_account: &global_account,
+        bonding_curve_account: bonding_curve_account.as_deref(),
+        ata_exists: associated_token_account_exists(rpc, payer, mint).await,
+    };
+
+    offline::build_buy_instructions(payer, mint, &state, amount_sol, slippage_basis_points)
+}
 
-    instructions.push(instruction::buy(
-        payer,
-        mint,
-        &global_account.fee_recipient,
-        instruction::Buy {
-            _amount: buy_amount,
-            _max_sol_cost: buy_amount_with_slippage,
-        },
-    ));
-
-    let tip_account = jito_client.get_tip_account().await.map_err(|e| anyhow!(e))?;
-
-    let jito_fee = jito_fee.unwrap_or(JITO_TIP_AMOUNT);
-    instructions.push(
-        system_instruction::transfer(
-            &payer.pubkey(),
-            &tip_account,
-            sol_to_lamports(jito_fee),
-        ),
-    );
+/// Builds Jito buy instructions for an exact token amount
+#[allow(clippy::too_many_arguments)]
+pub async fn build_buy_exact_tokens_instructions_with_jito(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    jito_client: &JitoClient,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_token: u64,
+    slippage_basis_points: Option<u64>,
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<Vec<Instruction>> {
+    let mut instructions = build_buy_exact_tokens_bundle_instructions(rpc, account_cache, payer, mint, amount_token, slippage_basis_points).await?;
+    let tip_lamports = jito_client.resolve_tip(tip_strategy.unwrap_or_default()).await?;
+    instructions.push(build_jito_tip_instruction(jito_client, payer, tip_lamports).await?);
 
     Ok(instructions)
 }
+
+async fn build_buy_exact_tokens_bundle_instructions(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_token: u64,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Vec<Instruction>> {
+    if amount_token == 0 {
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
+    }
+
+    let global_account = account_cache.get_global_account(rpc).await?;
+    let bonding_curve_account = account_cache.get_bonding_curve_account(rpc, mint).await?;
+    let state = TradeState {
+        global_account: &global_account,
+        bonding_curve_account: Some(&bonding_curve_account),
+        ata_exists: associated_token_account_exists(rpc, payer, mint).await,
+    };
+
+    offline::build_buy_exact_tokens_instructions(payer, mint, &state, amount_token, slippage_basis_points)
+}
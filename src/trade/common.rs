@@ -1,33 +1,54 @@
-use anyhow::anyhow;
 use std::sync::Arc;
-use solana_client::rpc_client::RpcClient;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig, rpc_response::RpcSimulateTransactionResult};
 use solana_sdk::{
-    compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction, transaction::Transaction
+    address_lookup_table::AddressLookupTableAccount, commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction, hash::Hash, instruction::Instruction, message::{v0, Message, VersionedMessage}, pubkey::Pubkey, signer::Signer, system_instruction, transaction::{Transaction, VersionedTransaction}
 };
 use spl_associated_token_account::get_associated_token_address;
-use crate::{accounts, common::logs_data::TradeInfo, constants::{self, trade::{DEFAULT_COMPUTE_UNIT_LIMIT, DEFAULT_COMPUTE_UNIT_PRICE, DEFAULT_SLIPPAGE}}};
+use crate::{accounts, common::{fee_estimator::{FeeEstimator, FeeStrategy}, logs_data::TradeInfo}, constants::{self, trade::{DEFAULT_COMPUTE_UNIT_LIMIT, DEFAULT_COMPUTE_UNIT_PRICE, DEFAULT_SLIPPAGE}}, error::{ClientError, ClientResult, TransactionFailure}, jito::JitoClient};
 use borsh::BorshDeserialize;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PriorityFee {
     pub limit: Option<u32>,
     pub price: Option<u64>,
+    /// Estimates the unit price from recent fees when the trade is built over RPC, `price` is used
+    /// by builders without RPC access
+    pub auto: Option<AutoPriorityFee>,
 }
 
 impl Default for PriorityFee {
     fn default() -> Self {
-        Self { limit: Some(DEFAULT_COMPUTE_UNIT_LIMIT), price: Some(DEFAULT_COMPUTE_UNIT_PRICE) }
+        Self { limit: Some(DEFAULT_COMPUTE_UNIT_LIMIT), price: Some(DEFAULT_COMPUTE_UNIT_PRICE), auto: None }
     }
 }
 
-pub async fn transfer_sol(rpc: &RpcClient, payer: &Keypair, receive_wallet: &Pubkey, amount: u64) -> Result<(), anyhow::Error> {
+impl PriorityFee {
+    /// A unit price picked by `strategy` from the recent fees of the traded Pump.fun accounts, at
+    /// most `max_price`
+    pub fn auto(strategy: FeeStrategy, max_price: u64) -> Self {
+        Self {
+            auto: Some(AutoPriorityFee { strategy, max_price }),
+            ..Self::default()
+        }
+    }
+}
+
+/// Unit price estimated from recent prioritization fees, see [`FeeEstimator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoPriorityFee {
+    pub strategy: FeeStrategy,
+    /// Highest unit price in micro-lamports
+    pub max_price: u64,
+}
+
+pub async fn transfer_sol(rpc: &RpcClient, payer: &dyn Signer, receive_wallet: &Pubkey, amount: u64) -> ClientResult<()> {
     if amount == 0 {
-        return Err(anyhow!("Amount cannot be zero"));
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
     }
 
-    let balance = get_sol_balance(rpc, &payer.pubkey())?;
+    let balance = get_sol_balance(rpc, &payer.pubkey()).await?;
     if balance < amount {
-        return Err(anyhow!("Insufficient balance"));
+        return Err(ClientError::InsufficientBalance { required: amount, available: balance });
     }
 
     let transfer_instruction = system_instruction::transfer(
@@ -36,16 +57,11 @@ pub async fn transfer_sol(rpc: &RpcClient, payer: &Keypair, receive_wallet: &Pub
         amount,
     );
 
-    let recent_blockhash = rpc.get_latest_blockhash()?;
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
 
-    let transaction = Transaction::new_signed_with_payer(
-        &[transfer_instruction],
-        Some(&payer.pubkey()),
-        &[payer],
-        recent_blockhash,
-    );  
+    let transaction = build_transaction(&payer.pubkey(), &[transfer_instruction], &[payer], recent_blockhash)?;
 
-    rpc.send_and_confirm_transaction(&transaction)?;
+    rpc.send_and_confirm_transaction(&transaction).await?;
 
     Ok(())
 }
@@ -64,19 +80,19 @@ pub fn create_priority_fee_instructions(priority_fee: Option<PriorityFee>) -> Ve
     instructions
 }
 
-pub fn get_token_balance(rpc: &RpcClient, account: &Pubkey, mint: &Pubkey) -> Result<u64, anyhow::Error> {
+pub async fn get_token_balance(rpc: &RpcClient, account: &Pubkey, mint: &Pubkey) -> ClientResult<u64> {
     let ata = get_associated_token_address(account, mint);
-    if rpc.get_account(&ata).is_err() {
+    if rpc.get_account(&ata).await.is_err() {
         return Ok(0);
     }
 
-    let balance = rpc.get_token_account_balance(&ata)?;
+    let balance = rpc.get_token_account_balance(&ata).await?;
     balance.amount.parse::<u64>()
-        .map_err(|_| anyhow!("Failed to parse token balance"))
+        .map_err(|e| ClientError::Parse("Failed to parse token balance".to_string(), e.to_string()))
 }
 
-pub fn get_sol_balance(rpc: &RpcClient, account: &Pubkey) -> Result<u64, anyhow::Error> {
-    rpc.get_balance(account).map_err(|_| anyhow!("Failed to get SOL balance"))
+pub async fn get_sol_balance(rpc: &RpcClient, account: &Pubkey) -> ClientResult<u64> {
+    Ok(rpc.get_balance(account).await?)
 }
 
 #[inline]
@@ -124,17 +140,17 @@ pub fn get_metadata_pda(mint: &Pubkey) -> Pubkey {
 }
 
 #[inline]
-pub async fn get_global_account(rpc: &RpcClient) -> Result<Arc<accounts::GlobalAccount>, anyhow::Error> {
+pub async fn get_global_account(rpc: &RpcClient) -> ClientResult<Arc<accounts::GlobalAccount>> {
     let global = get_global_pda();
 
-    let account = rpc.get_account(&global)?;
-    let global_account = Arc::new(accounts::GlobalAccount::try_from_slice(&account.data)?); 
+    let account = rpc.get_account(&global).await?;
+    let global_account = Arc::new(accounts::GlobalAccount::try_from_slice(&account.data).map_err(ClientError::BorshError)?); 
     
     Ok(global_account)
 }
 
 #[inline]
-pub async fn get_initial_buy_price(global_account: &Arc<accounts::GlobalAccount>, amount_sol: u64) -> Result<u64, anyhow::Error> {
+pub async fn get_initial_buy_price(global_account: &Arc<accounts::GlobalAccount>, amount_sol: u64) -> ClientResult<u64> {
     let buy_amount = global_account.get_initial_buy_price(amount_sol);
     Ok(buy_amount)
 }
@@ -143,19 +159,143 @@ pub async fn get_initial_buy_price(global_account: &Arc<accounts::GlobalAccount>
 pub async fn get_bonding_curve_account(
     rpc: &RpcClient,
     mint: &Pubkey,
-) -> Result<Arc<accounts::BondingCurveAccount>, anyhow::Error> {
+) -> ClientResult<Arc<accounts::BondingCurveAccount>> {
     let bonding_curve_pda = get_bonding_curve_pda(mint)
-        .ok_or(anyhow!("Bonding curve not found"))?;
-    
-    if rpc.get_account(&bonding_curve_pda).is_err() {
-        return Err(anyhow!("Bonding curve not found"));
+        .ok_or(ClientError::BondingCurveNotFound)?;
+
+    let account = rpc.get_account_with_commitment(&bonding_curve_pda, rpc.commitment()).await?
+        .value
+        .ok_or(ClientError::BondingCurveNotFound)?;
+    let bonding_curve = Arc::new(accounts::BondingCurveAccount::try_from_slice(&account.data).map_err(ClientError::BorshError)?);
+
+    if bonding_curve.complete {
+        return Err(ClientError::BondingCurveComplete);
     }
 
-    let account = rpc.get_account(&bonding_curve_pda)?;
-    let bonding_curve = Arc::new(accounts::BondingCurveAccount::try_from_slice(&account.data)?);
     Ok(bonding_curve)
 }
 
+/// Returns true if the associated token account of `owner` for `mint` exists
+pub async fn associated_token_account_exists(rpc: &RpcClient, owner: &Pubkey, mint: &Pubkey) -> bool {
+    let ata = get_associated_token_address(owner, mint);
+    rpc.get_account(&ata).await.is_ok()
+}
+
+/// Puts compute budget instructions in front of `instructions`
+///
+/// The unit limit is measured by a simulation. The unit price comes from the priority fee, or is
+/// estimated from the recent fees of the bonding curve of `mint` and the Global PDA, by the
+/// priority fee's auto mode or the median without a priority fee.
+pub async fn add_compute_budget_instructions(
+    rpc: &RpcClient,
+    fee_estimator: &FeeEstimator,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    instructions: Vec<Instruction>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<Vec<Instruction>> {
+    let mut instructions = [
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            ComputeBudgetInstruction::set_compute_unit_price(0),
+        ],
+        instructions,
+    ].concat();
+
+    let result_cu = simulate_instructions(rpc, payer, &instructions).await?;
+
+    let (unit_price, max_price) = match priority_fee {
+        None => (fee_estimator.estimate_for_mint(rpc, mint, FeeStrategy::default()).await?, u64::MAX),
+        Some(PriorityFee { auto: Some(auto), price, .. }) => {
+            let estimate = fee_estimator.estimate_for_mint(rpc, mint, auto.strategy).await?;
+            (estimate.or(price), auto.max_price)
+        }
+        Some(pf) => (pf.price, u64::MAX),
+    };
+
+    let unit_price = match unit_price {
+        None | Some(0) => DEFAULT_COMPUTE_UNIT_PRICE,
+        Some(price) => price,
+    }.min(max_price);
+
+    instructions[0] = ComputeBudgetInstruction::set_compute_unit_limit(result_cu as u32);
+    instructions[1] = ComputeBudgetInstruction::set_compute_unit_price(unit_price);
+
+    Ok(instructions)
+}
+
+/// Builds and signs a legacy transaction
+pub fn build_transaction(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    signers: &[&dyn Signer],
+    recent_blockhash: Hash,
+) -> ClientResult<Transaction> {
+    let mut transaction = Transaction::new_with_payer(instructions, Some(payer));
+    transaction.try_sign(signers, recent_blockhash)?;
+    Ok(transaction)
+}
+
+/// Builds a legacy transaction with the blockhash set, for a separate signer to sign
+pub fn build_unsigned_transaction(payer: &Pubkey, instructions: &[Instruction], recent_blockhash: Hash) -> Transaction {
+    let mut message = Message::new(instructions, Some(payer));
+    message.recent_blockhash = recent_blockhash;
+    Transaction::new_unsigned(message)
+}
+
+/// Simulates the instructions without signatures and returns the compute units they consumed
+pub async fn simulate_instructions(rpc: &RpcClient, payer: &Pubkey, instructions: &[Instruction]) -> ClientResult<u64> {
+    let simulate_tx = Transaction::new_with_payer(instructions, Some(payer));
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        commitment: Some(CommitmentConfig::confirmed()),
+        ..RpcSimulateTransactionConfig::default()
+    };
+
+    let result = rpc.simulate_transaction_with_config(&simulate_tx, config).await?
+        .value;
+
+    get_simulation_units_consumed(&result)
+}
+
+/// Builds and signs a transaction, as v0 with the given lookup tables or as legacy without any
+pub fn build_versioned_transaction(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    signers: &[&dyn Signer],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> ClientResult<VersionedTransaction> {
+    let message = if lookup_tables.is_empty() {
+        VersionedMessage::Legacy(Message::new_with_blockhash(instructions, Some(payer), &recent_blockhash))
+    } else {
+        VersionedMessage::V0(v0::Message::try_compile(payer, instructions, lookup_tables, recent_blockhash)?)
+    };
+
+    Ok(VersionedTransaction::try_new(message, signers)?)
+}
+
+/// Builds a Jito tip transfer from `payer` to one of the block engine's tip accounts
+pub async fn build_jito_tip_instruction(jito_client: &JitoClient, payer: &Pubkey, lamports: u64) -> ClientResult<Instruction> {
+    let tip_account = jito_client.get_tip_account().await?;
+    Ok(system_instruction::transfer(payer, &tip_account, lamports))
+}
+
+/// Checks a transaction simulation and returns the compute units it consumed
+pub fn get_simulation_units_consumed(result: &RpcSimulateTransactionResult) -> ClientResult<u64> {
+    if let Some(failure) = TransactionFailure::from_simulation(result) {
+        return Err(ClientError::simulation_failed(failure));
+    }
+
+    if result.logs.as_ref().is_none_or(|logs| logs.is_empty()) {
+        return Err(ClientError::InvalidData("Simulation returned no logs".to_string()));
+    }
+
+    result.units_consumed
+        .ok_or_else(|| ClientError::InvalidData("Simulation returned no compute units".to_string()))
+}
+
 #[inline]
 pub fn get_buy_amount_with_slippage(amount_sol: u64, slippage_basis_points: Option<u64>) -> u64 {
     let slippage = slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE);
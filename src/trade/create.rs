@@ -1,32 +1,31 @@
 use std::time::Instant;
 
-use anyhow::anyhow;
-use solana_client::{rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction, instruction::Instruction, native_token::sol_to_lamports, signature::{Keypair, Signature}, signer::Signer, system_instruction, transaction::Transaction
-};
-use spl_associated_token_account::{
-    get_associated_token_address,
-    instruction::create_associated_token_account,
+    address_lookup_table::AddressLookupTableAccount, instruction::Instruction, pubkey::Pubkey, signer::Signer, transaction::{Transaction, VersionedTransaction}
 };
+use crate::{common::{account_cache::AccountCache, blockhash_cache::BlockhashCache, fee_estimator::FeeEstimator}, error::{ClientError, ClientResult}, instruction, ipfs::TokenMetadataIPFS, jito::{tip::TipStrategy, JitoClient}, trade::buy::build_buy_bundle_instructions};
 
-use crate::{constants::{self, trade::{DEFAULT_COMPUTE_UNIT_PRICE, JITO_TIP_AMOUNT}}, instruction, ipfs::TokenMetadataIPFS, jito::JitoClient, trade::buy::build_buy_transaction_with_jito};
+use super::bundle::{plan_and_send_bundles, BundleLeg, BundleReport, BundleTip, TipDistribution};
 
-use super::common::{create_priority_fee_instructions, get_buy_amount_with_slippage, get_global_account, PriorityFee};
+use super::common::{add_compute_budget_instructions, associated_token_account_exists, build_jito_tip_instruction, build_transaction, build_unsigned_transaction, build_versioned_transaction, create_priority_fee_instructions, PriorityFee};
+use super::offline::{self, TradeState};
+use super::submit::{submit_transaction, TransactionOutcome};
 
-/// Create a new token
+/// Create a new token, the transaction is rebroadcast until it lands or its blockhash expires
 pub async fn create(
     rpc: &RpcClient,
-    payer: &Keypair,
-    mint: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    payer: &dyn Signer,
+    mint: &dyn Signer,
     ipfs: TokenMetadataIPFS,
     priority_fee: Option<PriorityFee>,
-) -> Result<Signature, anyhow::Error> {
+) -> ClientResult<TransactionOutcome> {
     let mut instructions = create_priority_fee_instructions(priority_fee);
 
     instructions.push(instruction::create(
-        payer,
-        mint,
+        &payer.pubkey(),
+        &mint.pubkey(),
         instruction::Create {
             _name: ipfs.metadata.name,
             _symbol: ipfs.metadata.symbol,
@@ -34,83 +33,103 @@ pub async fn create(
         },
     ));
 
-    let recent_blockhash = rpc.get_latest_blockhash()?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer, mint],
-        recent_blockhash,
-    );
-
-    let signature = rpc.send_and_confirm_transaction(&transaction)?;
-
-    Ok(signature)
+    let latest = blockhash_cache.get_latest_blockhash().await?;
+    let transaction = build_transaction(&payer.pubkey(), &instructions, &[payer, mint], latest.blockhash)?;
+    submit_transaction(rpc, &transaction, latest.last_valid_block_height, None).await
 }
 
 /// Create and buy tokens in one transaction
+#[allow(clippy::too_many_arguments)]
 pub async fn create_and_buy(
     rpc: &RpcClient,
-    payer: &Keypair,
-    mint: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &dyn Signer,
     ipfs: TokenMetadataIPFS,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Signature, anyhow::Error> {
+) -> ClientResult<TransactionOutcome> {
     if amount_sol == 0 {
-        return Err(anyhow!("Amount cannot be zero"));
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
     }
 
-    let transaction = build_create_and_buy_transaction(rpc, payer, mint, ipfs, amount_sol, slippage_basis_points, priority_fee).await?;
-    let signature = rpc.send_and_confirm_transaction(&transaction)?;
-
-    Ok(signature)
+    let instructions = build_create_and_buy_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), &mint.pubkey(), ipfs, amount_sol, slippage_basis_points, priority_fee).await?;
+    let latest = blockhash_cache.get_latest_blockhash().await?;
+    let transaction = build_transaction(&payer.pubkey(), &instructions, &[payer, mint], latest.blockhash)?;
+    submit_transaction(rpc, &transaction, latest.last_valid_block_height, None).await
 }
 
+/// Create a token and buy it from several wallets using Jito
+///
+/// The first payer creates the token. The create transaction always leads the first bundle, and
+/// the buys of the remaining payers are packed after it. Each bundle pays the tip of `tip_strategy`
+/// once, as set by `tip_distribution`. With lookup tables the transactions are v0. Returns which
+/// wallets landed in which bundle.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_and_buy_list_with_jito(
     rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
     jito_client: &JitoClient,
-    payers: Vec<&Keypair>,
-    mint: &Keypair,
+    payers: Vec<&dyn Signer>,
+    mint: &dyn Signer,
     ipfs: TokenMetadataIPFS,
     amount_sols: Vec<u64>,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<String, anyhow::Error> {
-    
+    tip_strategy: Option<TipStrategy>,
+    tip_distribution: TipDistribution<'_>,
+    lookup_tables: &[AddressLookupTableAccount],
+) -> ClientResult<Vec<BundleReport>> {
+    if payers.is_empty() || payers.len() != amount_sols.len() {
+        return Err(ClientError::InvalidInput("Each payer needs exactly one amount"));
+    }
+
     let start_time = Instant::now();
 
-    let mut transactions = Vec::new();
-    let transaction = build_create_and_buy_transaction_with_jito(rpc, jito_client, payers[0], mint, ipfs, amount_sols[0], slippage_basis_points, jito_fee).await?;
-    transactions.push(transaction);
-    
-    for (i, payer) in payers.iter().skip(1).enumerate() {
-        println!("Creating and buying token index: {}", i);
-        let buy_transaction = build_buy_transaction_with_jito(rpc, jito_client, payer, &mint.pubkey(), amount_sols[i], slippage_basis_points, jito_fee).await?;
-        transactions.push(buy_transaction);
+    let create_instructions = build_create_and_buy_bundle_instructions(rpc, account_cache, &payers[0].pubkey(), &mint.pubkey(), ipfs, amount_sols[0], slippage_basis_points).await?;
+    let mut legs = vec![BundleLeg {
+        payer: payers[0],
+        signers: vec![mint],
+        instructions: create_instructions,
+    }];
+
+    for (payer, amount_sol) in payers.into_iter().zip(amount_sols).skip(1) {
+        let instructions = build_buy_bundle_instructions(rpc, account_cache, &payer.pubkey(), &mint.pubkey(), amount_sol, slippage_basis_points).await?;
+        legs.push(BundleLeg::new(payer, instructions));
     }
 
-    let signatures = jito_client.send_transactions(&transactions).await?;
+    let tip = BundleTip {
+        account: jito_client.get_tip_account().await?,
+        lamports: jito_client.resolve_tip(tip_strategy.unwrap_or_default()).await?,
+        distribution: tip_distribution,
+    };
+    let reports = plan_and_send_bundles(jito_client, legs, &tip, lookup_tables, blockhash_cache).await?;
 
     println!("Total Jito create and buy operation time: {:?}ms", start_time.elapsed().as_millis());
-    
-    Ok(signatures)
+
+    Ok(reports)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_and_buy_with_jito(
     rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
     jito_client: &JitoClient,
-    payer: &Keypair,
-    mint: &Keypair,
+    payer: &dyn Signer,
+    mint: &dyn Signer,
     ipfs: TokenMetadataIPFS,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<String, anyhow::Error> {
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<String> {
 
     let start_time = Instant::now();
 
-    let transaction = build_create_and_buy_transaction_with_jito(rpc, jito_client, payer, mint, ipfs, amount_sol, slippage_basis_points, jito_fee).await?;
+    let transaction = build_create_and_buy_transaction_with_jito(rpc, blockhash_cache, account_cache, jito_client, payer, mint, ipfs, amount_sol, slippage_basis_points, tip_strategy).await?;
 
     let signature = jito_client.send_transaction(&transaction).await?;
 
@@ -119,208 +138,165 @@ pub async fn create_and_buy_with_jito(
     Ok(signature)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn build_create_and_buy_transaction(
     rpc: &RpcClient,
-    payer: &Keypair,
-    mint: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &dyn Signer,
     ipfs: TokenMetadataIPFS,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Transaction, anyhow::Error> {
-    let instructions = build_create_and_buy_instructions(rpc, payer, mint, ipfs, amount_sol, slippage_basis_points, priority_fee).await?;
-    let recent_blockhash = rpc.get_latest_blockhash()?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer, mint],
-        recent_blockhash,
-    );
+) -> ClientResult<Transaction> {
+    let instructions = build_create_and_buy_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), &mint.pubkey(), ipfs, amount_sol, slippage_basis_points, priority_fee).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_transaction(&payer.pubkey(), &instructions, &[payer, mint], recent_blockhash)
+}
 
-    Ok(transaction)
+/// Builds an unsigned create and buy transaction, to be signed by the payer and the mint
+#[allow(clippy::too_many_arguments)]
+pub async fn build_create_and_buy_unsigned_transaction(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    ipfs: TokenMetadataIPFS,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<Transaction> {
+    let instructions = build_create_and_buy_instructions(rpc, account_cache, fee_estimator, payer, mint, ipfs, amount_sol, slippage_basis_points, priority_fee).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    Ok(build_unsigned_transaction(payer, &instructions, recent_blockhash))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn build_create_and_buy_transaction_with_jito(
     rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
     jito_client: &JitoClient,
-    payer: &Keypair,
-    mint: &Keypair,
+    payer: &dyn Signer,
+    mint: &dyn Signer,
     ipfs: TokenMetadataIPFS,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<Transaction, anyhow::Error> {
-    let instructions = build_create_and_buy_instructions_with_jito(rpc, jito_client, payer, mint, ipfs, amount_sol, slippage_basis_points, jito_fee).await?;
-    let recent_blockhash = rpc.get_latest_blockhash()?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer, mint],
-        recent_blockhash,
-    );
-
-    Ok(transaction)
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<Transaction> {
+    let instructions = build_create_and_buy_instructions_with_jito(rpc, account_cache, jito_client, &payer.pubkey(), &mint.pubkey(), ipfs, amount_sol, slippage_basis_points, tip_strategy).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_transaction(&payer.pubkey(), &instructions, &[payer, mint], recent_blockhash)
 }
 
-pub async fn build_create_and_buy_instructions(
+/// Builds a v0 create and buy transaction that loads shared accounts from the lookup tables
+#[allow(clippy::too_many_arguments)]
+pub async fn build_create_and_buy_versioned_transaction(
     rpc: &RpcClient,
-    payer: &Keypair,
-    mint: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &dyn Signer,
     ipfs: TokenMetadataIPFS,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Vec<Instruction>, anyhow::Error> {
-    if amount_sol == 0 {
-        return Err(anyhow!("Amount cannot be zero"));
-    }
-
-    let global_account = get_global_account(rpc).await?;
-    let buy_amount = global_account.get_initial_buy_price(amount_sol);
-    let buy_amount_with_slippage =
-        get_buy_amount_with_slippage(amount_sol, slippage_basis_points);
-
-    let mut instructions = vec![
-        ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
-        ComputeBudgetInstruction::set_compute_unit_price(0),
-    ];
-
-    instructions.push(instruction::create(
-        payer,
-        mint,
-        instruction::Create {
-            _name: ipfs.metadata.name,
-            _symbol: ipfs.metadata.symbol,
-            _uri: ipfs.metadata_uri,
-        },
-    ));
-
-    let ata = get_associated_token_address(&payer.pubkey(), &mint.pubkey());
-    if rpc.get_account(&ata).is_err() {
-        instructions.push(create_associated_token_account(
-            &payer.pubkey(),
-            &payer.pubkey(),
-            &mint.pubkey(),
-            &constants::accounts::TOKEN_PROGRAM,
-        ));
-    }
-
-    instructions.push(instruction::buy(
-        payer,
-        &mint.pubkey(),
-        &global_account.fee_recipient,
-        instruction::Buy {
-            _amount: buy_amount,
-            _max_sol_cost: buy_amount_with_slippage,
-        },
-    ));
-
-    let commitment_config = CommitmentConfig::confirmed();
-    let recent_blockhash = rpc.get_latest_blockhash_with_commitment(commitment_config)?
-        .0;
-
-    let simulate_tx = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer, mint],
-        recent_blockhash,
-    );
-
-    let config = RpcSimulateTransactionConfig {
-        sig_verify: true,
-        commitment: Some(commitment_config),
-        ..RpcSimulateTransactionConfig::default()
-    };
-    
-    let result = rpc.simulate_transaction_with_config(&simulate_tx, config)?
-        .value;
-
-    if result.logs.as_ref().map_or(true, |logs| logs.is_empty()) {
-        return Err(anyhow!("Simulation failed: {:?}", result.err));
-    }
-
-    let result_cu = result.units_consumed.ok_or_else(|| anyhow!("No compute units consumed"))?;
-    let fees = rpc.get_recent_prioritization_fees(&[])?;
-    let average_fees = if fees.is_empty() {
-        DEFAULT_COMPUTE_UNIT_PRICE
-    } else {
-        fees.iter()
-            .map(|fee| fee.prioritization_fee)
-            .sum::<u64>() / fees.len() as u64
-    };
-
-    let unit_price = match priority_fee {
-        None => average_fees,
-        Some(pf) => pf.price.unwrap_or(DEFAULT_COMPUTE_UNIT_PRICE)
-    };
-
-    let unit_price = if unit_price == 0 { DEFAULT_COMPUTE_UNIT_PRICE } else { unit_price };
+    lookup_tables: &[AddressLookupTableAccount],
+) -> ClientResult<VersionedTransaction> {
+    let instructions = build_create_and_buy_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), &mint.pubkey(), ipfs, amount_sol, slippage_basis_points, priority_fee).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_versioned_transaction(&payer.pubkey(), &instructions, &[payer, mint], lookup_tables, recent_blockhash)
+}
 
-    instructions[0] = ComputeBudgetInstruction::set_compute_unit_limit(result_cu as u32);
-    instructions[1] = ComputeBudgetInstruction::set_compute_unit_price(unit_price);
+/// Builds a v0 Jito create and buy transaction that loads shared accounts from the lookup tables
+#[allow(clippy::too_many_arguments)]
+pub async fn build_create_and_buy_versioned_transaction_with_jito(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    jito_client: &JitoClient,
+    payer: &dyn Signer,
+    mint: &dyn Signer,
+    ipfs: TokenMetadataIPFS,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    tip_strategy: Option<TipStrategy>,
+    lookup_tables: &[AddressLookupTableAccount],
+) -> ClientResult<VersionedTransaction> {
+    let instructions = build_create_and_buy_instructions_with_jito(rpc, account_cache, jito_client, &payer.pubkey(), &mint.pubkey(), ipfs, amount_sol, slippage_basis_points, tip_strategy).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_versioned_transaction(&payer.pubkey(), &instructions, &[payer, mint], lookup_tables, recent_blockhash)
+}
 
-    Ok(instructions)
+#[allow(clippy::too_many_arguments)]
+pub async fn build_create_and_buy_instructions(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    ipfs: TokenMetadataIPFS,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<Vec<Instruction>> {
+    let instructions = build_create_and_buy_bundle_instructions(rpc, account_cache, payer, mint, ipfs, amount_sol, slippage_basis_points).await?;
+    add_compute_budget_instructions(rpc, fee_estimator, payer, mint, instructions, priority_fee).await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn build_create_and_buy_instructions_with_jito(
     rpc: &RpcClient,
+    account_cache: &AccountCache,
     jito_client: &JitoClient,
-    payer: &Keypair,
-    mint: &Keypair,
+    payer: &Pubkey,
+    mint: &Pubkey,
     ipfs: TokenMetadataIPFS,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<Vec<Instruction>, anyhow::Error> {
-    if amount_sol == 0 {
-        return Err(anyhow!("Amount cannot be zero"));
-    }
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<Vec<Instruction>> {
+    let mut instructions = build_create_and_buy_bundle_instructions(rpc, account_cache, payer, mint, ipfs, amount_sol, slippage_basis_points).await?;
+    let tip_lamports = jito_client.resolve_tip(tip_strategy.unwrap_or_default()).await?;
+    instructions.push(build_jito_tip_instruction(jito_client, payer, tip_lamports * 2).await?);
 
-    let global_account = get_global_account(rpc).await?;
-    let buy_amount = global_account.get_initial_buy_price(amount_sol);
-    let buy_amount_with_slippage =
-        get_buy_amount_with_slippage(amount_sol, slippage_basis_points);
+    Ok(instructions)
+}
 
-    let mut instructions = vec![];
-    instructions.push(instruction::create(
+/// Builds create and buy instructions for a bundle, without a Jito tip
+///
+/// The bundle planner adds the tip, see `trade::bundle::TipDistribution`.
+pub async fn build_create_and_buy_bundle_instructions(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    ipfs: TokenMetadataIPFS,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Vec<Instruction>> {
+    let global_account = account_cache.get_global_account(rpc).await?;
+    let state = TradeState {
+        global_account: &global_account,
+        bonding_curve_account: None,
+        ata_exists: associated_token_account_exists(rpc, payer, mint).await,
+    };
+
+    offline::build_create_and_buy_instructions(
         payer,
         mint,
+        &state,
         instruction::Create {
             _name: ipfs.metadata.name,
             _symbol: ipfs.metadata.symbol,
             _uri: ipfs.metadata_uri,
         },
-    ));
-
-    let ata = get_associated_token_address(&payer.pubkey(), &mint.pubkey());
-    if rpc.get_account(&ata).is_err() {
-        instructions.push(create_associated_token_account(
-            &payer.pubkey(),
-            &payer.pubkey(),
-            &mint.pubkey(),
-            &constants::accounts::TOKEN_PROGRAM,
-        ));
-    }
-
-    instructions.push(instruction::buy(
-        payer,
-        &mint.pubkey(),
-        &global_account.fee_recipient,
-        instruction::Buy {
-            _amount: buy_amount,
-            _max_sol_cost: buy_amount_with_slippage,
-        },
-    ));
-
-    let tip_account = jito_client.get_tip_account().await.map_err(|e| anyhow!(e))?;
-    let jito_fee = jito_fee.unwrap_or(JITO_TIP_AMOUNT);
-    instructions.push(
-        system_instruction::transfer(
-            &payer.pubkey(),
-            &tip_account,
-            sol_to_lamports(jito_fee * 2.0),
-        ),
-    );
-
-    Ok(instructions)
+        amount_sol,
+        slippage_basis_points,
+    )
 }
@@ -0,0 +1,114 @@
+//! Address lookup tables for Pump.fun transactions
+//!
+//! Every Pump.fun instruction references the same program and PDA accounts. A v0 transaction
+//! that loads them from an address lookup table pays one byte per account instead of 32, which
+//! leaves room for more buys in a transaction.
+//!
+//! Addresses added to a table can only be used from the slot after they were added.
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    address_lookup_table::{
+        instruction::{create_lookup_table, extend_lookup_table},
+        state::AddressLookupTable,
+        AddressLookupTableAccount,
+    },
+    commitment_config::CommitmentConfig,
+    compute_budget,
+    pubkey::Pubkey,
+    signer::Signer,
+};
+
+use crate::{constants, error::{ClientError, ClientResult}};
+
+use super::common::{build_transaction, get_global_account, get_global_pda, get_mint_authority_pda};
+
+/// Maximum number of addresses added by one extend transaction
+pub const MAX_EXTEND_ADDRESSES: usize = 20;
+
+/// Returns the accounts shared by Pump.fun create, buy and sell transactions
+pub fn pumpfun_lookup_table_addresses(fee_recipient: &Pubkey) -> Vec<Pubkey> {
+    vec![
+        constants::accounts::PUMPFUN,
+        get_global_pda(),
+        *fee_recipient,
+        constants::accounts::EVENT_AUTHORITY,
+        get_mint_authority_pda(),
+        constants::accounts::MPL_TOKEN_METADATA,
+        constants::accounts::SYSTEM_PROGRAM,
+        constants::accounts::TOKEN_PROGRAM,
+        constants::accounts::ASSOCIATED_TOKEN_PROGRAM,
+        constants::accounts::RENT,
+        compute_budget::id(),
+    ]
+}
+
+/// Creates a lookup table holding the Pump.fun accounts, owned by `authority`
+pub async fn create_pumpfun_lookup_table(
+    rpc: &RpcClient,
+    authority: &dyn Signer,
+) -> ClientResult<AddressLookupTableAccount> {
+    let global_account = get_global_account(rpc).await?;
+    let addresses = pumpfun_lookup_table_addresses(&global_account.fee_recipient);
+    create_address_lookup_table(rpc, authority, &addresses).await
+}
+
+/// Creates a lookup table with the given addresses, owned and paid for by `authority`
+pub async fn create_address_lookup_table(
+    rpc: &RpcClient,
+    authority: &dyn Signer,
+    addresses: &[Pubkey],
+) -> ClientResult<AddressLookupTableAccount> {
+    // The table address is derived from a slot that must still be in the slot hashes sysvar
+    let recent_slot = rpc.get_slot_with_commitment(CommitmentConfig::finalized()).await?;
+    let (create_instruction, table) = create_lookup_table(authority.pubkey(), authority.pubkey(), recent_slot);
+
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    let transaction = build_transaction(&authority.pubkey(), &[create_instruction], &[authority], recent_blockhash)?;
+    rpc.send_and_confirm_transaction(&transaction).await?;
+
+    extend_address_lookup_table(rpc, authority, &table, addresses).await?;
+
+    Ok(AddressLookupTableAccount {
+        key: table,
+        addresses: addresses.to_vec(),
+    })
+}
+
+/// Adds addresses to a lookup table owned by `authority`
+pub async fn extend_address_lookup_table(
+    rpc: &RpcClient,
+    authority: &dyn Signer,
+    table: &Pubkey,
+    addresses: &[Pubkey],
+) -> ClientResult<()> {
+    for chunk in addresses.chunks(MAX_EXTEND_ADDRESSES) {
+        let instruction = extend_lookup_table(
+            *table,
+            authority.pubkey(),
+            Some(authority.pubkey()),
+            chunk.to_vec(),
+        );
+
+        let recent_blockhash = rpc.get_latest_blockhash().await?;
+        let transaction = build_transaction(&authority.pubkey(), &[instruction], &[authority], recent_blockhash)?;
+        rpc.send_and_confirm_transaction(&transaction).await?;
+    }
+
+    Ok(())
+}
+
+/// Fetches a lookup table and its addresses
+pub async fn get_address_lookup_table(
+    rpc: &RpcClient,
+    table: &Pubkey,
+) -> ClientResult<AddressLookupTableAccount> {
+    let account = rpc.get_account(table).await?;
+    let lookup_table = AddressLookupTable::deserialize(&account.data)
+        .map_err(|err| ClientError::Parse("Invalid address lookup table".to_string(), err.to_string()))?;
+
+    Ok(AddressLookupTableAccount {
+        key: *table,
+        addresses: lookup_table.addresses.to_vec(),
+    })
+}
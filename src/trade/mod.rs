@@ -1,4 +1,10 @@
 pub mod buy;
+pub mod bundle;
 pub mod create;
+pub mod lookup_table;
+pub mod offline;
+pub mod quote;
+pub mod retry;
 pub mod sell;
+pub mod submit;
 pub mod common;
\ No newline at end of file
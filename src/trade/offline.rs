@@ -0,0 +1,486 @@
+//! Trade builders that make no RPC calls
+//!
+//! The builders in `trade::buy`, `trade::sell` and `trade::create` fetch the accounts they need
+//! and simulate the transaction. The builders here take that state from the caller instead, for
+//! callers that already track it from an event stream, and return the same instructions.
+//!
+//! Without a simulation the compute unit limit comes from the `PriorityFee`, and defaults to
+//! `DEFAULT_COMPUTE_UNIT_LIMIT`.
+
+use solana_sdk::{hash::Hash, instruction::Instruction, pubkey::Pubkey, transaction::Transaction};
+use spl_associated_token_account::{get_associated_token_address, instruction::create_associated_token_account};
+use spl_token::instruction::close_account;
+
+use crate::{
+    accounts::{BondingCurveAccount, GlobalAccount},
+    constants::{self, trade::DEFAULT_SLIPPAGE},
+    error::{ClientError, ClientResult},
+    instruction,
+};
+
+use super::common::{build_unsigned_transaction, calculate_with_slippage_buy, calculate_with_slippage_sell, create_priority_fee_instructions, PriorityFee};
+use super::quote::quote_buy_with_budget;
+
+/// On-chain state a trade is built from
+#[derive(Debug, Clone, Copy)]
+pub struct TradeState<'a> {
+    pub global_account: &'a GlobalAccount,
+    /// Bonding curve of the mint, None if the curve is created earlier in the same bundle
+    pub bonding_curve_account: Option<&'a BondingCurveAccount>,
+    /// Whether the payer's associated token account for the mint exists
+    pub ata_exists: bool,
+}
+
+impl<'a> TradeState<'a> {
+    /// Returns the bonding curve, which must exist and not be complete
+    fn bonding_curve_account(&self) -> ClientResult<&'a BondingCurveAccount> {
+        let bonding_curve_account = self.bonding_curve_account.ok_or(ClientError::BondingCurveNotFound)?;
+        if bonding_curve_account.complete {
+            return Err(ClientError::BondingCurveComplete);
+        }
+
+        Ok(bonding_curve_account)
+    }
+
+    fn create_ata_instruction(&self, payer: &Pubkey, mint: &Pubkey) -> Option<Instruction> {
+        (!self.ata_exists).then(|| {
+            create_associated_token_account(payer, payer, mint, &constants::accounts::TOKEN_PROGRAM)
+        })
+    }
+}
+
+/// Builds buy instructions for `amount_sol`, without compute budget instructions
+///
+/// Without a bonding curve the token amount is 80% of the initial buy price, as the curve is
+/// expected to be created earlier in the same bundle.
+pub fn build_buy_instructions(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    state: &TradeState,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Vec<Instruction>> {
+    if amount_sol == 0 {
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
+    }
+
+    let buy_amount = match state.bonding_curve_account {
+        Some(_) => state.bonding_curve_account()?
+            .get_buy_price_with_fee(amount_sol, state.global_account.fee_basis_points)
+            .map_err(ClientError::BondingCurveError)?,
+        None => state.global_account.get_initial_buy_price_with_fee(amount_sol) * 80 / 100,
+    };
+    let max_sol_cost = calculate_with_slippage_buy(amount_sol, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE));
+
+    let mut instructions: Vec<Instruction> = state.create_ata_instruction(payer, mint).into_iter().collect();
+    instructions.push(instruction::buy(
+        payer,
+        mint,
+        &state.global_account.fee_recipient,
+        instruction::Buy {
+            _amount: buy_amount,
+            _max_sol_cost: max_sol_cost,
+        },
+    ));
+
+    Ok(instructions)
+}
+
+/// Builds buy instructions for an exact token amount, without compute budget instructions
+///
+/// The maximum SOL cost is the fee-inclusive curve cost of `amount_token`, widened by the slippage.
+pub fn build_buy_exact_tokens_instructions(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    state: &TradeState,
+    amount_token: u64,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Vec<Instruction>> {
+    if amount_token == 0 {
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
+    }
+
+    let sol_cost = state.bonding_curve_account()?
+        .get_buy_sol_cost(amount_token, state.global_account.fee_basis_points)
+        .map_err(ClientError::BondingCurveError)?;
+    let max_sol_cost = calculate_with_slippage_buy(sol_cost, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE));
+
+    let mut instructions: Vec<Instruction> = state.create_ata_instruction(payer, mint).into_iter().collect();
+    instructions.push(instruction::buy(
+        payer,
+        mint,
+        &state.global_account.fee_recipient,
+        instruction::Buy {
+            _amount: amount_token,
+            _max_sol_cost: max_sol_cost,
+        },
+    ));
+
+    Ok(instructions)
+}
+
+/// Builds buy instructions that spend at most `budget_sol`, without compute budget instructions
+///
+/// The max SOL cost is the budget, and the token amount is the quote for the budget less the
+/// slippage, see [`quote_buy_with_budget`].
+pub fn build_buy_with_budget_instructions(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    state: &TradeState,
+    budget_sol: u64,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Vec<Instruction>> {
+    let quote = quote_buy_with_budget(state.global_account, state.bonding_curve_account()?, budget_sol, slippage_basis_points)?;
+
+    let mut instructions: Vec<Instruction> = state.create_ata_instruction(payer, mint).into_iter().collect();
+    instructions.push(instruction::buy(
+        payer,
+        mint,
+        &state.global_account.fee_recipient,
+        instruction::Buy {
+            _amount: quote.token_amount,
+            _max_sol_cost: quote.sol_limit,
+        },
+    ));
+
+    Ok(instructions)
+}
+
+/// Builds sell instructions, without compute budget instructions
+///
+/// Sells the whole `token_balance` if `amount_token` is None, and closes the token account when
+/// it is left empty.
+pub fn build_sell_instructions(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    state: &TradeState,
+    token_balance: u64,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Vec<Instruction>> {
+    let token_balance = if state.ata_exists { token_balance } else { 0 };
+    let amount = amount_token.unwrap_or(token_balance);
+
+    if amount == 0 {
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
+    }
+
+    if amount > token_balance {
+        return Err(ClientError::InsufficientBalance { required: amount, available: token_balance });
+    }
+
+    let min_sol_output = state.bonding_curve_account()?
+        .get_sell_price(amount, state.global_account.fee_basis_points)
+        .map_err(ClientError::BondingCurveError)?;
+    let min_sol_output_with_slippage = calculate_with_slippage_sell(
+        min_sol_output,
+        slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+    );
+
+    let mut instructions = vec![instruction::sell(
+        payer,
+        mint,
+        &state.global_account.fee_recipient,
+        instruction::Sell {
+            _amount: amount,
+            _min_sol_output: min_sol_output_with_slippage,
+        },
+    )];
+
+    // The token account can only be closed once it is empty
+    if amount == token_balance {
+        let ata = get_associated_token_address(payer, mint);
+        instructions.push(close_account(
+            &spl_token::ID,
+            &ata,
+            payer,
+            payer,
+            &[payer],
+        ).map_err(|e| ClientError::Solana("Failed to build close account instruction".to_string(), e.to_string()))?);
+    }
+
+    Ok(instructions)
+}
+
+/// Builds create and buy instructions, without compute budget instructions
+///
+/// The token amount is the initial buy price of `amount_sol`, as the curve is created by the same
+/// transaction.
+pub fn build_create_and_buy_instructions(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    state: &TradeState,
+    create: instruction::Create,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Vec<Instruction>> {
+    if amount_sol == 0 {
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
+    }
+
+    let buy_amount = state.global_account.get_initial_buy_price_with_fee(amount_sol);
+    let max_sol_cost = calculate_with_slippage_buy(amount_sol, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE));
+
+    let mut instructions = vec![instruction::create(payer, mint, create)];
+    instructions.extend(state.create_ata_instruction(payer, mint));
+    instructions.push(instruction::buy(
+        payer,
+        mint,
+        &state.global_account.fee_recipient,
+        instruction::Buy {
+            _amount: buy_amount,
+            _max_sol_cost: max_sol_cost,
+        },
+    ));
+
+    Ok(instructions)
+}
+
+/// Builds an unsigned buy transaction
+pub fn build_buy_transaction(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    state: &TradeState,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+    recent_blockhash: Hash,
+) -> ClientResult<Transaction> {
+    let instructions = build_buy_instructions(payer, mint, state, amount_sol, slippage_basis_points)?;
+    Ok(build_priced_transaction(payer, instructions, priority_fee, recent_blockhash))
+}
+
+/// Builds an unsigned sell transaction
+#[allow(clippy::too_many_arguments)]
+pub fn build_sell_transaction(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    state: &TradeState,
+    token_balance: u64,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+    recent_blockhash: Hash,
+) -> ClientResult<Transaction> {
+    let instructions = build_sell_instructions(payer, mint, state, token_balance, amount_token, slippage_basis_points)?;
+    Ok(build_priced_transaction(payer, instructions, priority_fee, recent_blockhash))
+}
+
+/// Builds an unsigned create and buy transaction, to be signed by the payer and the mint
+#[allow(clippy::too_many_arguments)]
+pub fn build_create_and_buy_transaction(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    state: &TradeState,
+    create: instruction::Create,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+    recent_blockhash: Hash,
+) -> ClientResult<Transaction> {
+    let instructions = build_create_and_buy_instructions(payer, mint, state, create, amount_sol, slippage_basis_points)?;
+    Ok(build_priced_transaction(payer, instructions, priority_fee, recent_blockhash))
+}
+
+fn build_priced_transaction(
+    payer: &Pubkey,
+    instructions: Vec<Instruction>,
+    priority_fee: Option<PriorityFee>,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let mut priced_instructions = create_priority_fee_instructions(priority_fee);
+    priced_instructions.extend(instructions);
+    build_unsigned_transaction(payer, &priced_instructions, recent_blockhash)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::compute_budget;
+
+    use super::*;
+
+    fn get_global_account() -> GlobalAccount {
+        GlobalAccount::new(
+            1,
+            true,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            1_073_000_000_000_000,
+            30_000_000_000,
+            793_100_000_000_000,
+            1_000_000_000_000_000,
+            100,
+        )
+    }
+
+    fn get_bonding_curve_account(complete: bool) -> BondingCurveAccount {
+        BondingCurveAccount::new(
+            1,
+            1_073_000_000_000_000,
+            30_000_000_000,
+            793_100_000_000_000,
+            0,
+            1_000_000_000_000_000,
+            complete,
+        )
+    }
+
+    /// Returns the token amount and maximum SOL cost of a buy instruction
+    fn decode_buy(instruction: &Instruction) -> (u64, u64) {
+        let amount = u64::from_le_bytes(instruction.data[8..16].try_into().unwrap());
+        let max_sol_cost = u64::from_le_bytes(instruction.data[16..24].try_into().unwrap());
+        (amount, max_sol_cost)
+    }
+
+    #[test]
+    fn test_build_buy_instructions() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let global_account = get_global_account();
+        let bonding_curve_account = get_bonding_curve_account(false);
+
+        let mut state = TradeState {
+            global_account: &global_account,
+            bonding_curve_account: Some(&bonding_curve_account),
+            ata_exists: false,
+        };
+        let instructions = build_buy_instructions(&payer, &mint, &state, 1_000_000_000, Some(500)).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].program_id, constants::accounts::ASSOCIATED_TOKEN_PROGRAM);
+        assert_eq!(instructions[1].program_id, constants::accounts::PUMPFUN);
+
+        let (amount, max_sol_cost) = decode_buy(&instructions[1]);
+        assert_eq!(amount, bonding_curve_account.get_buy_price_with_fee(1_000_000_000, global_account.fee_basis_points).unwrap());
+        assert_eq!(max_sol_cost, 1_050_000_000);
+        assert_eq!(instructions[1].accounts[2].pubkey, mint);
+
+        state.ata_exists = true;
+        let instructions = build_buy_instructions(&payer, &mint, &state, 1_000_000_000, Some(500)).unwrap();
+        assert_eq!(instructions.len(), 1);
+
+        // The curve is created earlier in the bundle
+        state.bonding_curve_account = None;
+        let instructions = build_buy_instructions(&payer, &mint, &state, 1_000_000_000, Some(500)).unwrap();
+        assert_eq!(decode_buy(&instructions[0]).0, global_account.get_initial_buy_price_with_fee(1_000_000_000) * 80 / 100);
+
+        assert!(matches!(
+            build_buy_instructions(&payer, &mint, &state, 0, None),
+            Err(ClientError::InvalidInput(_))
+        ));
+
+        let complete = get_bonding_curve_account(true);
+        state.bonding_curve_account = Some(&complete);
+        assert!(matches!(
+            build_buy_instructions(&payer, &mint, &state, 1_000_000_000, None),
+            Err(ClientError::BondingCurveComplete)
+        ));
+    }
+
+    #[test]
+    fn test_build_buy_with_budget_instructions() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let global_account = get_global_account();
+        let bonding_curve_account = get_bonding_curve_account(false);
+        let state = TradeState {
+            global_account: &global_account,
+            bonding_curve_account: Some(&bonding_curve_account),
+            ata_exists: true,
+        };
+
+        let instructions = build_buy_with_budget_instructions(&payer, &mint, &state, 1_000_000_000, Some(500)).unwrap();
+        assert_eq!(instructions.len(), 1);
+
+        // The budget is the max SOL cost, the slippage comes off the token amount
+        let (amount, max_sol_cost) = decode_buy(&instructions[0]);
+        let quoted_amount = bonding_curve_account.get_buy_price_with_fee(1_000_000_000, global_account.fee_basis_points).unwrap();
+        assert_eq!(amount, quoted_amount - quoted_amount * 500 / 10000);
+        assert_eq!(max_sol_cost, 1_000_000_000);
+        assert!(bonding_curve_account.get_buy_sol_cost(amount, global_account.fee_basis_points).unwrap() <= max_sol_cost);
+
+        let state = TradeState { bonding_curve_account: None, ..state };
+        assert!(matches!(
+            build_buy_with_budget_instructions(&payer, &mint, &state, 1_000_000_000, None),
+            Err(ClientError::BondingCurveNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_build_sell_instructions() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let global_account = get_global_account();
+        let bonding_curve_account = get_bonding_curve_account(false);
+        let state = TradeState {
+            global_account: &global_account,
+            bonding_curve_account: Some(&bonding_curve_account),
+            ata_exists: true,
+        };
+
+        // Selling everything closes the token account
+        let instructions = build_sell_instructions(&payer, &mint, &state, 1_000_000, None, None).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[1].program_id, spl_token::ID);
+        assert_eq!(instructions[1].accounts[0].pubkey, get_associated_token_address(&payer, &mint));
+
+        let instructions = build_sell_instructions(&payer, &mint, &state, 1_000_000, Some(400_000), None).unwrap();
+        assert_eq!(instructions.len(), 1);
+
+        assert!(matches!(
+            build_sell_instructions(&payer, &mint, &state, 1_000_000, Some(2_000_000), None),
+            Err(ClientError::InsufficientBalance { required: 2_000_000, available: 1_000_000 })
+        ));
+
+        let state = TradeState { bonding_curve_account: None, ..state };
+        assert!(matches!(
+            build_sell_instructions(&payer, &mint, &state, 1_000_000, None, None),
+            Err(ClientError::BondingCurveNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_build_create_and_buy_transaction() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let global_account = get_global_account();
+        let state = TradeState {
+            global_account: &global_account,
+            bonding_curve_account: None,
+            ata_exists: false,
+        };
+        let create = instruction::Create {
+            _name: "Lorem ipsum".to_string(),
+            _symbol: "LIP".to_string(),
+            _uri: "https://example.com".to_string(),
+        };
+        let recent_blockhash = Hash::new_unique();
+
+        let transaction = build_create_and_buy_transaction(
+            &payer,
+            &mint,
+            &state,
+            create,
+            1_000_000_000,
+            None,
+            Some(PriorityFee::default()),
+            recent_blockhash,
+        ).unwrap();
+
+        let message = &transaction.message;
+        assert_eq!(message.recent_blockhash, recent_blockhash);
+        assert_eq!(message.account_keys[0], payer);
+        assert_eq!(message.header.num_required_signatures, 2);
+        assert!(transaction.signatures.iter().all(|signature| *signature == Default::default()));
+
+        let programs: Vec<Pubkey> = message.instructions.iter()
+            .map(|instruction| message.account_keys[instruction.program_id_index as usize])
+            .collect();
+        assert_eq!(programs, vec![
+            compute_budget::id(),
+            compute_budget::id(),
+            constants::accounts::PUMPFUN,
+            constants::accounts::ASSOCIATED_TOKEN_PROGRAM,
+            constants::accounts::PUMPFUN,
+        ]);
+    }
+}
@@ -0,0 +1,382 @@
+//! Trade previews
+//!
+//! A `Quote` is what a buy or sell built against the given accounts would do on the curve: the
+//! amounts it trades, the slippage bound it carries, the protocol fee, its price impact and the
+//! curve reserves after it. Quoting makes no RPC calls and sends nothing.
+
+use crate::{
+    accounts::{BondingCurveAccount, GlobalAccount},
+    constants::trade::DEFAULT_SLIPPAGE,
+    error::{ClientError, ClientResult},
+};
+
+use super::common::{calculate_with_slippage_buy, calculate_with_slippage_sell};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// Preview of a buy or sell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    pub side: TradeSide,
+    /// Tokens bought or sold
+    pub token_amount: u64,
+    /// SOL a buy pays including the fee, or a sell receives net of the fee
+    pub sol_amount: u64,
+    /// Slippage bound of the instruction, the max SOL cost of a buy or the min SOL output of a
+    /// sell
+    pub sol_limit: u64,
+    /// Protocol fee in lamports, from `GlobalAccount::fee_basis_points`
+    pub fee: u64,
+    /// How much worse the average price of the trade is than the curve price before it, in
+    /// basis points
+    pub price_impact_basis_points: u64,
+    /// Virtual SOL reserves after the trade
+    pub virtual_sol_reserves: u64,
+    /// Virtual token reserves after the trade
+    pub virtual_token_reserves: u64,
+    /// Whether the trade buys the last tokens of the curve and completes it
+    pub completes_curve: bool,
+}
+
+impl Quote {
+    /// Returns the expected amount the trade takes out, tokens for a buy and SOL for a sell
+    pub fn expected_amount(&self) -> u64 {
+        match self.side {
+            TradeSide::Buy => self.token_amount,
+            TradeSide::Sell => self.sol_amount,
+        }
+    }
+}
+
+fn check_bonding_curve(bonding_curve: &BondingCurveAccount) -> ClientResult<()> {
+    if bonding_curve.complete {
+        return Err(ClientError::BondingCurveComplete);
+    }
+    Ok(())
+}
+
+/// Returns how far `sol_amount` for `token_amount` is from the curve price, in basis points
+fn price_impact_basis_points(bonding_curve: &BondingCurveAccount, sol_amount: u64, token_amount: u64, side: TradeSide) -> u64 {
+    if token_amount == 0 || bonding_curve.virtual_sol_reserves == 0 {
+        return 0;
+    }
+
+    // The average price over the curve price, as virtual reserves are a price of SOL per token
+    let ratio = (sol_amount as u128) * (bonding_curve.virtual_token_reserves as u128) * 10000
+        / ((token_amount as u128) * (bonding_curve.virtual_sol_reserves as u128));
+    let impact = match side {
+        TradeSide::Buy => ratio.saturating_sub(10000),
+        TradeSide::Sell => 10000u128.saturating_sub(ratio),
+    };
+    impact as u64
+}
+
+/// Quotes a buy of `amount_sol`, as built by `trade::offline::build_buy_instructions`
+///
+/// The tokens are the most `amount_sol` buys once the fee is paid out of it. The slippage widens
+/// the SOL cost, so the buy may spend more than `amount_sol` if the curve moves first.
+pub fn quote_buy(
+    global_account: &GlobalAccount,
+    bonding_curve: &BondingCurveAccount,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Quote> {
+    if amount_sol == 0 {
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
+    }
+    check_bonding_curve(bonding_curve)?;
+
+    let token_amount = bonding_curve.get_buy_price_with_fee(amount_sol, global_account.fee_basis_points).map_err(ClientError::BondingCurveError)?;
+    let sol_limit = calculate_with_slippage_buy(amount_sol, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE));
+    quote_token_buy(global_account, bonding_curve, token_amount, sol_limit)
+}
+
+/// Quotes a buy that spends at most `budget_sol`, as built by
+/// `trade::offline::build_buy_with_budget_instructions`
+///
+/// The max SOL cost is the budget itself, and the slippage lowers the token amount instead: the
+/// buy takes the tokens the budget is quoted for after the fee, less the slippage.
+pub fn quote_buy_with_budget(
+    global_account: &GlobalAccount,
+    bonding_curve: &BondingCurveAccount,
+    budget_sol: u64,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Quote> {
+    if budget_sol == 0 {
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
+    }
+    check_bonding_curve(bonding_curve)?;
+
+    let quoted_amount = bonding_curve.get_buy_price_with_fee(budget_sol, global_account.fee_basis_points).map_err(ClientError::BondingCurveError)?;
+    let token_amount = calculate_with_slippage_sell(quoted_amount, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE).min(10000));
+    if token_amount == 0 {
+        return Err(ClientError::InvalidInput("Budget is too small to buy any tokens"));
+    }
+
+    quote_token_buy(global_account, bonding_curve, token_amount, budget_sol)
+}
+
+/// Quotes a buy of exactly `token_amount` with a max SOL cost of `sol_limit`
+fn quote_token_buy(
+    global_account: &GlobalAccount,
+    bonding_curve: &BondingCurveAccount,
+    token_amount: u64,
+    sol_limit: u64,
+) -> ClientResult<Quote> {
+    let (curve_cost, sol_amount) = if token_amount == 0 {
+        (0, 0)
+    } else {
+        (
+            bonding_curve.get_buy_sol_cost(token_amount, 0).map_err(ClientError::BondingCurveError)?,
+            bonding_curve.get_buy_sol_cost(token_amount, global_account.fee_basis_points).map_err(ClientError::BondingCurveError)?,
+        )
+    };
+
+    Ok(Quote {
+        side: TradeSide::Buy,
+        token_amount,
+        sol_amount,
+        sol_limit,
+        fee: sol_amount - curve_cost,
+        price_impact_basis_points: price_impact_basis_points(bonding_curve, curve_cost, token_amount, TradeSide::Buy),
+        virtual_sol_reserves: bonding_curve.virtual_sol_reserves.saturating_add(curve_cost),
+        virtual_token_reserves: bonding_curve.virtual_token_reserves - token_amount,
+        completes_curve: token_amount > 0 && token_amount == bonding_curve.real_token_reserves,
+    })
+}
+
+/// Quotes a sell of `amount_token`, as built by `trade::offline::build_sell_instructions`
+///
+/// The fee is deducted from the SOL the curve pays out.
+pub fn quote_sell(
+    global_account: &GlobalAccount,
+    bonding_curve: &BondingCurveAccount,
+    amount_token: u64,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Quote> {
+    if amount_token == 0 {
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
+    }
+    check_bonding_curve(bonding_curve)?;
+
+    let curve_output = bonding_curve.get_sell_price(amount_token, 0).map_err(ClientError::BondingCurveError)?;
+    let sol_amount = bonding_curve.get_sell_price(amount_token, global_account.fee_basis_points)
+        .map_err(ClientError::BondingCurveError)?;
+
+    Ok(Quote {
+        side: TradeSide::Sell,
+        token_amount: amount_token,
+        sol_amount,
+        sol_limit: calculate_with_slippage_sell(sol_amount, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE)),
+        fee: curve_output - sol_amount,
+        price_impact_basis_points: price_impact_basis_points(bonding_curve, curve_output, amount_token, TradeSide::Sell),
+        virtual_sol_reserves: bonding_curve.virtual_sol_reserves - curve_output,
+        virtual_token_reserves: bonding_curve.virtual_token_reserves.saturating_add(amount_token),
+        completes_curve: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{native_token::sol_to_lamports, pubkey::Pubkey};
+
+    use crate::common::logs_data::TradeInfo;
+
+    use super::*;
+
+    fn global_account() -> GlobalAccount {
+        GlobalAccount::new(1, true, Pubkey::new_unique(), Pubkey::new_unique(), 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 1_000_000_000_000_000, 100)
+    }
+
+    fn bonding_curve() -> BondingCurveAccount {
+        BondingCurveAccount::new(1, 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 0, 1_000_000_000_000_000, false)
+    }
+
+    #[test]
+    fn test_quote_buy() {
+        let global_account = global_account();
+        let bonding_curve = bonding_curve();
+        let amount_sol = sol_to_lamports(1.0);
+
+        let quote = quote_buy(&global_account, &bonding_curve, amount_sol, Some(500)).unwrap();
+        assert_eq!(quote.side, TradeSide::Buy);
+        assert_eq!(quote.token_amount, bonding_curve.get_buy_price_with_fee(amount_sol, 100).unwrap());
+        assert_eq!(quote.expected_amount(), quote.token_amount);
+        assert_eq!(quote.sol_amount, bonding_curve.get_buy_sol_cost(quote.token_amount, 100).unwrap());
+        assert_eq!(quote.sol_limit, amount_sol + amount_sol * 500 / 10000);
+        // The 1% fee is paid out of the amount, so the buy spends no more than it
+        assert!(quote.sol_amount <= amount_sol);
+        assert!(quote.token_amount < bonding_curve.get_buy_price(amount_sol).unwrap());
+        assert_eq!(quote.fee, (quote.sol_amount - quote.fee) / 100);
+        // The 0.99 SOL left after the fee, on 30 SOL of virtual reserves, moves the price by 0.99/30
+        assert_eq!(quote.price_impact_basis_points, 330);
+        assert_eq!(quote.virtual_sol_reserves, bonding_curve.virtual_sol_reserves + quote.sol_amount - quote.fee);
+        assert_eq!(quote.virtual_token_reserves, bonding_curve.virtual_token_reserves - quote.token_amount);
+        assert!(!quote.completes_curve);
+
+        // Buying more than is left takes the remaining tokens and completes the curve
+        let quote = quote_buy(&global_account, &bonding_curve, sol_to_lamports(1_000.0), None).unwrap();
+        assert_eq!(quote.token_amount, bonding_curve.real_token_reserves);
+        assert!(quote.completes_curve);
+
+        assert!(matches!(quote_buy(&global_account, &bonding_curve, 0, None), Err(ClientError::InvalidInput(_))));
+        let complete = BondingCurveAccount { complete: true, ..bonding_curve };
+        assert!(matches!(quote_buy(&global_account, &complete, amount_sol, None), Err(ClientError::BondingCurveComplete)));
+    }
+
+    #[test]
+    fn test_quote_buy_with_budget() {
+        let global_account = global_account();
+        let bonding_curve = bonding_curve();
+        let budget_sol = sol_to_lamports(1.0);
+
+        let quote = quote_buy_with_budget(&global_account, &bonding_curve, budget_sol, Some(500)).unwrap();
+        let quoted_amount = bonding_curve.get_buy_price_with_fee(budget_sol, 100).unwrap();
+        assert_eq!(quote.token_amount, quoted_amount - quoted_amount * 500 / 10000);
+        assert_eq!(quote.sol_limit, budget_sol);
+        assert!(quote.sol_amount <= budget_sol);
+        assert_eq!(quote.sol_amount, bonding_curve.get_buy_sol_cost(quote.token_amount, 100).unwrap());
+
+        // The default 30% slippage still never spends more than the budget
+        let quote = quote_buy_with_budget(&global_account, &bonding_curve, budget_sol, None).unwrap();
+        assert_eq!(quote.sol_limit, budget_sol);
+        assert!(quote.sol_amount < budget_sol * 75 / 100);
+
+        // Without slippage the buy takes the quoted tokens, whose fee is already within the budget
+        let quote = quote_buy_with_budget(&global_account, &bonding_curve, budget_sol, Some(0)).unwrap();
+        assert_eq!(quote.token_amount, quoted_amount);
+        assert!(quote.sol_amount <= budget_sol);
+        assert!(bonding_curve.get_buy_sol_cost(quote.token_amount + 1, 100).unwrap() > budget_sol);
+    }
+
+    #[test]
+    fn test_quote_sell() {
+        let global_account = global_account();
+        let bonding_curve = bonding_curve();
+        let amount_token = 100_000_000_000_000;
+
+        let quote = quote_sell(&global_account, &bonding_curve, amount_token, Some(500)).unwrap();
+        assert_eq!(quote.side, TradeSide::Sell);
+        assert_eq!(quote.token_amount, amount_token);
+        assert_eq!(quote.sol_amount, bonding_curve.get_sell_price(amount_token, 100).unwrap());
+        assert_eq!(quote.expected_amount(), quote.sol_amount);
+        assert_eq!(quote.sol_limit, quote.sol_amount - quote.sol_amount * 500 / 10000);
+        assert_eq!(quote.fee, (quote.sol_amount + quote.fee) / 100);
+        assert_eq!(quote.virtual_sol_reserves, bonding_curve.virtual_sol_reserves - quote.sol_amount - quote.fee);
+        assert_eq!(quote.virtual_token_reserves, bonding_curve.virtual_token_reserves + amount_token);
+        // Selling moves the price the other way, by the share the sale adds to the token reserves
+        assert_eq!(quote.price_impact_basis_points, 853);
+        assert!(!quote.completes_curve);
+    }
+
+    fn trade_event(
+        is_buy: bool,
+        sol_amount: u64,
+        token_amount: u64,
+        virtual_sol_reserves: u64,
+        virtual_token_reserves: u64,
+        real_sol_reserves: u64,
+        real_token_reserves: u64,
+    ) -> TradeInfo {
+        TradeInfo {
+            is_buy,
+            sol_amount,
+            token_amount,
+            virtual_sol_reserves,
+            virtual_token_reserves,
+            real_sol_reserves,
+            real_token_reserves,
+            ..TradeInfo::default()
+        }
+    }
+
+    /// A trade and the reserves of the bonding curve before it
+    struct TradeFixture {
+        bonding_curve: BondingCurveAccount,
+        event: TradeInfo,
+    }
+
+    fn trade_fixture(reserves_before: [u64; 4], event: TradeInfo) -> TradeFixture {
+        let [virtual_sol_reserves, virtual_token_reserves, real_sol_reserves, real_token_reserves] = reserves_before;
+        TradeFixture {
+            bonding_curve: BondingCurveAccount::new(1, virtual_token_reserves, virtual_sol_reserves, real_token_reserves, real_sol_reserves, 1_000_000_000_000_000, false),
+            event,
+        }
+    }
+
+    /// Trades on a new curve, in order, laid out like the program's `TradeEvent`: the curve cost of
+    /// the trade before the fee and the reserves after it
+    ///
+    /// They are not decoded from on-chain transactions. They were worked out by hand with the same
+    /// constant-product rounding as the program, the buys as SOL amounts spent in full the way
+    /// `quote_buy` sends them, so they pin the quote arithmetic and the reserve bookkeeping, not
+    /// agreement with the program.
+    fn trade_fixtures() -> Vec<TradeFixture> {
+        vec![
+            trade_fixture(
+                [30_000_000_000, 1_073_000_000_000_000, 0, 793_100_000_000_000],
+                trade_event(true, 990_099_010, 34_281_150_163_062, 30_990_099_010, 1_038_718_849_836_938, 990_099_010, 758_818_849_836_938),
+            ),
+            trade_fixture(
+                [30_990_099_010, 1_038_718_849_836_938, 990_099_010, 758_818_849_836_938],
+                trade_event(true, 4_059_405_941, 120_303_595_612_880, 35_049_504_951, 918_415_254_224_058, 5_049_504_951, 638_515_254_224_058),
+            ),
+            trade_fixture(
+                [35_049_504_951, 918_415_254_224_058, 5_049_504_951, 638_515_254_224_058],
+                trade_event(false, 1_462_810_813, 40_000_000_000_000, 33_586_694_138, 958_415_254_224_058, 3_586_694_138, 678_515_254_224_058),
+            ),
+            trade_fixture(
+                [33_586_694_138, 958_415_254_224_058, 3_586_694_138, 678_515_254_224_058],
+                trade_event(true, 82_574_258, 2_350_524_149_875, 33_669_268_396, 956_064_730_074_183, 3_669_268_396, 676_164_730_074_183),
+            ),
+            trade_fixture(
+                [33_669_268_396, 956_064_730_074_183, 3_669_268_396, 676_164_730_074_183],
+                trade_event(false, 3_669_268_395, 116_935_269_925_817, 30_000_000_001, 1_073_000_000_000_000, 1, 793_100_000_000_000),
+            ),
+            trade_fixture(
+                [30_000_000_001, 1_073_000_000_000_000, 1, 793_100_000_000_000],
+                trade_event(true, 38_118_811_882, 600_443_313_950_305, 68_118_811_883, 472_556_686_049_695, 38_118_811_883, 192_656_686_049_695),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_quotes_reproduce_trade_fixtures() {
+        let global_account = global_account();
+        let fee_basis_points = global_account.fee_basis_points;
+
+        for TradeFixture { bonding_curve, event } in trade_fixtures() {
+            let quote = if event.is_buy {
+                // The SOL spent, fee included, buys exactly the event's tokens
+                let paid = event.sol_amount + event.sol_amount * fee_basis_points / 10000;
+                assert_eq!(bonding_curve.get_buy_price_with_fee(paid, fee_basis_points).unwrap(), event.token_amount);
+
+                let quote = quote_buy(&global_account, &bonding_curve, paid, Some(0)).unwrap();
+                assert_eq!(quote.token_amount, event.token_amount);
+                assert_eq!(quote.sol_amount, paid);
+                assert_eq!(quote.sol_amount - quote.fee, event.sol_amount);
+                quote
+            } else {
+                let quote = quote_sell(&global_account, &bonding_curve, event.token_amount, None).unwrap();
+                assert_eq!(quote.token_amount, event.token_amount);
+                assert_eq!(quote.sol_amount + quote.fee, event.sol_amount);
+                quote
+            };
+
+            assert_eq!(quote.virtual_sol_reserves, event.virtual_sol_reserves);
+            assert_eq!(quote.virtual_token_reserves, event.virtual_token_reserves);
+
+            // The real reserves move by the same amounts as the virtual ones
+            let (real_sol_reserves, real_token_reserves) = if event.is_buy {
+                (bonding_curve.real_sol_reserves + event.sol_amount, bonding_curve.real_token_reserves - event.token_amount)
+            } else {
+                (bonding_curve.real_sol_reserves - event.sol_amount, bonding_curve.real_token_reserves + event.token_amount)
+            };
+            assert_eq!((real_sol_reserves, real_token_reserves), (event.real_sol_reserves, event.real_token_reserves));
+        }
+    }
+}
@@ -0,0 +1,344 @@
+//! Retried trades
+//!
+//! A buy or sell that fails its slippage bound because the curve moved is quoted again against
+//! the current bonding curve and resubmitted with a wider slippage, as set by a `RetryPolicy`.
+//! Every attempt is logged in the returned `RetryReport`, including the fees of attempts that
+//! landed and failed.
+
+use std::{future::Future, time::Duration};
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+use tokio::time::Instant;
+
+use crate::{
+    common::{account_cache::AccountCache, blockhash_cache::BlockhashCache, fee_estimator::FeeEstimator},
+    constants::trade::{DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_DEADLINE, DEFAULT_RETRY_MAX_PRICE_MOVE, DEFAULT_RETRY_SLIPPAGE_STEP, DEFAULT_SLIPPAGE},
+    error::{ClientError, ClientResult},
+};
+
+use super::{buy::buy, common::PriorityFee, sell::sell, submit::TransactionOutcome};
+
+/// When and how a trade that failed its slippage bound is tried again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts including the first
+    pub max_attempts: u32,
+    /// Slippage added to each attempt after the first, in basis points
+    pub slippage_step_basis_points: u64,
+    /// Largest move of the curve price from the first attempt, in basis points, at which
+    /// another attempt is made
+    pub max_price_move_basis_points: u64,
+    /// Time after the first attempt during which another attempt may start
+    pub deadline: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_RETRY_ATTEMPTS,
+            slippage_step_basis_points: DEFAULT_RETRY_SLIPPAGE_STEP,
+            max_price_move_basis_points: DEFAULT_RETRY_MAX_PRICE_MOVE,
+            deadline: DEFAULT_RETRY_DEADLINE,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the slippage of the zero-based `attempt`
+    pub fn slippage_basis_points(&self, slippage_basis_points: u64, attempt: u32) -> u64 {
+        slippage_basis_points.saturating_add(self.slippage_step_basis_points.saturating_mul(attempt as u64))
+    }
+}
+
+/// Why a retried trade made no further attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryStop {
+    /// The last attempt landed
+    Landed,
+    /// The last attempt failed for a reason other than slippage or an expired blockhash
+    NotRetryable,
+    MaxAttempts,
+    /// The curve price moved further than the policy allows
+    PriceMoved,
+    Deadline,
+}
+
+/// One attempt of a retried trade
+#[derive(Debug)]
+pub struct TradeAttempt {
+    pub slippage_basis_points: u64,
+    /// Token price of the bonding curve the attempt was quoted at, None if the curve could not
+    /// be read
+    pub price: Option<f64>,
+    pub result: ClientResult<TransactionOutcome>,
+}
+
+impl TradeAttempt {
+    /// Returns true if the attempt failed in a way another attempt may fix: its slippage bound
+    /// was exceeded, in simulation or on chain, or its blockhash expired before it landed
+    pub fn is_retryable(&self) -> bool {
+        match &self.result {
+            Ok(TransactionOutcome::Failed { failure, .. }) => failure.is_slippage(),
+            Ok(TransactionOutcome::Expired { .. }) => true,
+            Ok(TransactionOutcome::Landed { .. }) => false,
+            Err(err) => matches!(err, ClientError::SlippageExceeded(_)),
+        }
+    }
+}
+
+/// Attempts of a retried trade, the last one holds the final result
+#[derive(Debug)]
+pub struct RetryReport {
+    pub attempts: Vec<TradeAttempt>,
+    pub stop: RetryStop,
+}
+
+impl RetryReport {
+    pub fn is_landed(&self) -> bool {
+        self.stop == RetryStop::Landed
+    }
+
+    /// Returns the fees paid by attempts that landed, successful or not, as far as the node
+    /// returned them
+    pub fn fees_paid(&self) -> u64 {
+        self.attempts.iter()
+            .filter_map(|attempt| match attempt.result {
+                Ok(TransactionOutcome::Landed { fee, .. } | TransactionOutcome::Failed { fee, .. }) => fee,
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Returns the result of the last attempt
+    pub fn into_result(self) -> ClientResult<TransactionOutcome> {
+        self.attempts.into_iter()
+            .last()
+            .map(|attempt| attempt.result)
+            .unwrap_or(Err(ClientError::InvalidInput("No attempt was made")))
+    }
+}
+
+/// Returns how far `price` moved from `first_price`, in basis points
+fn price_move_basis_points(first_price: f64, price: f64) -> u64 {
+    if first_price <= 0.0 {
+        return 0;
+    }
+    ((price - first_price).abs() / first_price * 10_000.0).round() as u64
+}
+
+/// Runs `trade` with the slippage of each attempt until it lands or `policy` stops it
+///
+/// The bonding curve of `mint` is read again before every attempt, to measure the price move and
+/// for `trade` to quote against.
+async fn retry_trade<F, Fut>(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    mint: &Pubkey,
+    slippage_basis_points: Option<u64>,
+    policy: RetryPolicy,
+    mut trade: F,
+) -> RetryReport
+where
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = ClientResult<TransactionOutcome>>,
+{
+    let start = Instant::now();
+    let slippage_basis_points = slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE);
+    let mut attempts: Vec<TradeAttempt> = Vec::new();
+    let mut first_price = None;
+
+    for attempt in 0..policy.max_attempts.max(1) {
+        if attempt > 0 && start.elapsed() >= policy.deadline {
+            return RetryReport { attempts, stop: RetryStop::Deadline };
+        }
+
+        let slippage_basis_points = policy.slippage_basis_points(slippage_basis_points, attempt);
+        let price = match account_cache.get_bonding_curve_account(rpc, mint).await {
+            Ok(bonding_curve) => bonding_curve.get_token_price(),
+            Err(err) => {
+                attempts.push(TradeAttempt { slippage_basis_points, price: None, result: Err(err) });
+                return RetryReport { attempts, stop: RetryStop::NotRetryable };
+            }
+        };
+
+        let first_price = *first_price.get_or_insert(price);
+        if price_move_basis_points(first_price, price) > policy.max_price_move_basis_points {
+            return RetryReport { attempts, stop: RetryStop::PriceMoved };
+        }
+
+        let attempt = TradeAttempt {
+            slippage_basis_points,
+            price: Some(price),
+            result: trade(slippage_basis_points).await,
+        };
+        let stop = if matches!(attempt.result, Ok(TransactionOutcome::Landed { .. })) {
+            Some(RetryStop::Landed)
+        } else if !attempt.is_retryable() {
+            Some(RetryStop::NotRetryable)
+        } else {
+            None
+        };
+
+        attempts.push(attempt);
+        if let Some(stop) = stop {
+            return RetryReport { attempts, stop };
+        }
+    }
+
+    RetryReport { attempts, stop: RetryStop::MaxAttempts }
+}
+
+/// Buy tokens, quoting and submitting again with a wider slippage while the buy fails its
+/// slippage bound, as set by `policy`
+#[allow(clippy::too_many_arguments)]
+pub async fn buy_with_retry(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+    policy: RetryPolicy,
+) -> RetryReport {
+    retry_trade(rpc, account_cache, mint, slippage_basis_points, policy, |slippage_basis_points| {
+        buy(rpc, blockhash_cache, account_cache, fee_estimator, payer, mint, amount_sol, Some(slippage_basis_points), priority_fee)
+    }).await
+}
+
+/// Sell tokens, quoting and submitting again with a wider slippage while the sell fails its
+/// slippage bound, as set by `policy`
+#[allow(clippy::too_many_arguments)]
+pub async fn sell_with_retry(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+    policy: RetryPolicy,
+) -> RetryReport {
+    retry_trade(rpc, account_cache, mint, slippage_basis_points, policy, |slippage_basis_points| {
+        sell(rpc, blockhash_cache, account_cache, fee_estimator, payer, mint, amount_token, Some(slippage_basis_points), priority_fee)
+    }).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use borsh::to_vec;
+    use solana_sdk::{
+        commitment_config::CommitmentConfig, instruction::InstructionError, signature::Signature, transaction::TransactionError,
+    };
+
+    use crate::{accounts::BondingCurveAccount, error::TransactionFailure};
+
+    use super::*;
+
+    fn slippage_failure() -> TransactionOutcome {
+        TransactionOutcome::Failed {
+            signature: Signature::new_unique(),
+            slot: 100,
+            fee: Some(5_000),
+            failure: Box::new(TransactionFailure::new(
+                TransactionError::InstructionError(2, InstructionError::Custom(6002)),
+                Vec::new(),
+            )),
+        }
+    }
+
+    fn landed() -> TransactionOutcome {
+        TransactionOutcome::Landed { signature: Signature::new_unique(), slot: 101, fee: Some(5_000) }
+    }
+
+    fn cached_curve(account_cache: &AccountCache, mint: &Pubkey, slot: u64, virtual_sol_reserves: u64) {
+        let bonding_curve = BondingCurveAccount::new(1, 1_000_000, virtual_sol_reserves, 500_000, 0, 1_000_000, false);
+        account_cache.update_bonding_curve_account(mint, slot, &to_vec(&bonding_curve).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.slippage_basis_points(1_000, 0), 1_000);
+        assert_eq!(policy.slippage_basis_points(1_000, 2), 2_000);
+        assert_eq!(policy.slippage_basis_points(u64::MAX, 1), u64::MAX);
+
+        assert_eq!(price_move_basis_points(1.0, 1.05), 500);
+        assert_eq!(price_move_basis_points(1.0, 0.9), 1_000);
+        assert_eq!(price_move_basis_points(0.0, 1.0), 0);
+    }
+
+    #[tokio::test]
+    async fn test_retry_trade_widens_slippage_until_landed() {
+        let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+        let account_cache = AccountCache::new(CommitmentConfig::confirmed());
+        let mint = Pubkey::new_unique();
+        cached_curve(&account_cache, &mint, 100, 1_000_000);
+
+        let calls = AtomicU32::new(0);
+        let report = retry_trade(&rpc, &account_cache, &mint, Some(1_000), RetryPolicy::default(), |_| async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Ok(slippage_failure()),
+                1 => Err(ClientError::SlippageExceeded(Box::new(TransactionFailure::new(
+                    TransactionError::InstructionError(0, InstructionError::Custom(6003)),
+                    Vec::new(),
+                )))),
+                _ => Ok(landed()),
+            }
+        }).await;
+
+        assert_eq!(report.stop, RetryStop::Landed);
+        let slippages: Vec<u64> = report.attempts.iter().map(|attempt| attempt.slippage_basis_points).collect();
+        assert_eq!(slippages, [1_000, 1_500, 2_000]);
+        assert_eq!(report.fees_paid(), 10_000);
+        assert!(report.into_result().unwrap().is_landed());
+    }
+
+    #[tokio::test]
+    async fn test_retry_trade_stops() {
+        let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+        let account_cache = AccountCache::new(CommitmentConfig::confirmed());
+        let mint = Pubkey::new_unique();
+        cached_curve(&account_cache, &mint, 100, 1_000_000);
+
+        // Out of attempts
+        let report = retry_trade(&rpc, &account_cache, &mint, None, RetryPolicy::default(), |_| async {
+            Ok(slippage_failure())
+        }).await;
+        assert_eq!((report.stop, report.attempts.len()), (RetryStop::MaxAttempts, 3));
+
+        // Failures other than slippage are not retried
+        let report = retry_trade(&rpc, &account_cache, &mint, None, RetryPolicy::default(), |_| async {
+            Err(ClientError::InvalidInput("Amount cannot be zero"))
+        }).await;
+        assert_eq!((report.stop, report.attempts.len()), (RetryStop::NotRetryable, 1));
+
+        // The curve moves 50% after the first attempt
+        let report = retry_trade(&rpc, &account_cache, &mint, None, RetryPolicy::default(), |_| {
+            cached_curve(&account_cache, &mint, 101, 1_500_000);
+            async { Ok(slippage_failure()) }
+        }).await;
+        assert_eq!((report.stop, report.attempts.len()), (RetryStop::PriceMoved, 1));
+
+        let policy = RetryPolicy { deadline: Duration::ZERO, ..RetryPolicy::default() };
+        let report = retry_trade(&rpc, &account_cache, &mint, None, policy, |_| async {
+            Ok(slippage_failure())
+        }).await;
+        assert_eq!((report.stop, report.attempts.len()), (RetryStop::Deadline, 1));
+
+        // A curve that cannot be read ends the trade
+        let report = retry_trade(&rpc, &account_cache, &Pubkey::new_unique(), None, RetryPolicy::default(), |_| async {
+            Ok(landed())
+        }).await;
+        assert_eq!(report.stop, RetryStop::NotRetryable);
+        assert!(report.attempts[0].price.is_none());
+        assert!(report.into_result().is_err());
+    }
+}
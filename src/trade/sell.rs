@@ -1,291 +1,315 @@
-use anyhow::anyhow;
-use solana_client::{rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction, instruction::Instruction, native_token::sol_to_lamports, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer, system_instruction, transaction::Transaction
+    address_lookup_table::AddressLookupTableAccount, instruction::Instruction, pubkey::Pubkey, signer::Signer, transaction::{Transaction, VersionedTransaction}
 };
 use spl_associated_token_account::get_associated_token_address;
-use spl_token::instruction::close_account;
 
 use std::time::Instant;
 
-use crate::{constants::trade::{DEFAULT_COMPUTE_UNIT_PRICE, DEFAULT_SLIPPAGE, JITO_TIP_AMOUNT}, instruction, jito::JitoClient};
+use crate::{common::{account_cache::AccountCache, blockhash_cache::BlockhashCache, fee_estimator::FeeEstimator}, error::{ClientError, ClientResult}, jito::{tip::TipStrategy, JitoClient, JitoSubmission}};
 
-use super::common::{calculate_with_slippage_sell, get_global_account, get_bonding_curve_account, PriorityFee};
+use super::common::{add_compute_budget_instructions, build_jito_tip_instruction, build_transaction, build_unsigned_transaction, build_versioned_transaction, PriorityFee};
+use super::offline::{self, TradeState};
+use super::submit::{submit_transaction, TransactionOutcome};
 
-async fn get_token_balance(rpc: &RpcClient, payer: &Keypair, mint: &Pubkey) -> Result<(u64, Pubkey), anyhow::Error> {
-    let ata = get_associated_token_address(&payer.pubkey(), mint);
-    let balance = rpc.get_token_account_balance(&ata)?;
+async fn get_token_balance(rpc: &RpcClient, payer: &Pubkey, mint: &Pubkey) -> ClientResult<u64> {
+    let ata = get_associated_token_address(payer, mint);
+    let balance = rpc.get_token_account_balance(&ata).await?;
     let balance_u64 = balance.amount.parse::<u64>()
-        .map_err(|_| anyhow!("Failed to parse token balance"))?;
+        .map_err(|e| ClientError::Parse("Failed to parse token balance".to_string(), e.to_string()))?;
     
     if balance_u64 == 0 {
-        return Err(anyhow!("Balance is 0"));
+        return Err(ClientError::InsufficientBalance { required: 1, available: 0 });
     }
 
-    Ok((balance_u64, ata))
+    Ok(balance_u64)
 }
 
+/// Sell tokens, the transaction is rebroadcast until it lands or its blockhash expires
+#[allow(clippy::too_many_arguments)]
 pub async fn sell(
     rpc: &RpcClient,
-    payer: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
     mint: &Pubkey,
     amount_token: Option<u64>,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Signature, anyhow::Error> {
-
-    let transaction = build_sell_transaction(rpc, payer, mint, amount_token, slippage_basis_points, priority_fee).await?;
-    let signature = rpc.send_and_confirm_transaction(&transaction)?;
-
-    Ok(signature)
+) -> ClientResult<TransactionOutcome> {
+    let instructions = build_sell_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), mint, amount_token, slippage_basis_points, priority_fee).await?;
+    let latest = blockhash_cache.get_latest_blockhash().await?;
+    let transaction = build_transaction(&payer.pubkey(), &instructions, &[payer], latest.blockhash)?;
+    submit_transaction(rpc, &transaction, latest.last_valid_block_height, None).await
 }
 
 /// Sell tokens by percentage
+#[allow(clippy::too_many_arguments)]
 pub async fn sell_by_percent(
     rpc: &RpcClient,
-    payer: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
     mint: &Pubkey,
     percent: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Signature, anyhow::Error> {
+) -> ClientResult<TransactionOutcome> {
     if percent == 0 || percent > 100 {
-        return Err(anyhow!("Percentage must be between 1 and 100"));
+        return Err(ClientError::InvalidInput("Percentage must be between 1 and 100"));
     }
 
-    let (balance_u64, _) = get_token_balance(rpc, payer, mint).await?;
+    let balance_u64 = get_token_balance(rpc, &payer.pubkey(), mint).await?;
     let amount = balance_u64 * percent / 100;
-    sell(rpc, payer, mint, Some(amount), slippage_basis_points, priority_fee).await
+    sell(rpc, blockhash_cache, account_cache, fee_estimator, payer, mint, Some(amount), slippage_basis_points, priority_fee).await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn sell_by_percent_with_jito(
     rpc: &RpcClient,
-    payer: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    payer: &dyn Signer,
     jito_client: &JitoClient,
     mint: &Pubkey,
     percent: u64,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<String, anyhow::Error> {
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<String> {
     if percent == 0 || percent > 100 {
-        return Err(anyhow!("Percentage must be between 1 and 100"));
+        return Err(ClientError::InvalidInput("Percentage must be between 1 and 100"));
     }
 
-    let (balance_u64, _) = get_token_balance(rpc, payer, mint).await?;
+    let balance_u64 = get_token_balance(rpc, &payer.pubkey(), mint).await?;
     let amount = balance_u64 * percent / 100;
-    sell_with_jito(rpc, payer, jito_client, mint, Some(amount), slippage_basis_points, jito_fee).await
+    sell_with_jito(rpc, blockhash_cache, account_cache, payer, jito_client, mint, Some(amount), slippage_basis_points, tip_strategy, None).await
 }
 
 /// Sell tokens using Jito
+///
+/// `submission` chooses between a bundle and a single transaction with revert protection, the
+/// Jito client's mode when None. Returns the bundle id or the signature.
+#[allow(clippy::too_many_arguments)]
 pub async fn sell_with_jito(
     rpc: &RpcClient,
-    payer: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    payer: &dyn Signer,
     jito_client: &JitoClient,
     mint: &Pubkey,
     amount_token: Option<u64>,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<String, anyhow::Error> {
+    tip_strategy: Option<TipStrategy>,
+    submission: Option<JitoSubmission>,
+) -> ClientResult<String> {
     let start_time = Instant::now();
 
-    let transaction = build_sell_transaction_with_jito(rpc, jito_client, payer, mint, amount_token, slippage_basis_points, jito_fee).await?;
-    let signature = jito_client.send_transaction(&transaction).await?;
+    let transaction = build_sell_transaction_with_jito(rpc, blockhash_cache, account_cache, jito_client, payer, mint, amount_token, slippage_basis_points, tip_strategy).await?;
+    let signature = match submission {
+        Some(submission) => jito_client.send_transaction_with_submission(&transaction, submission).await?,
+        None => jito_client.send_transaction(&transaction).await?,
+    };
     
     println!("Total Jito sell operation time: {:?}ms, signature: {}", start_time.elapsed().as_millis(), signature);
 
     Ok(signature)
 }
 
+/// Sell enough tokens to receive a target amount of SOL, net of fees
+#[allow(clippy::too_many_arguments)]
+pub async fn sell_for_sol(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<TransactionOutcome> {
+    let amount = get_sell_amount_for_sol(rpc, account_cache, &payer.pubkey(), mint, amount_sol).await?;
+    sell(rpc, blockhash_cache, account_cache, fee_estimator, payer, mint, Some(amount), slippage_basis_points, priority_fee).await
+}
+
+/// Sell enough tokens to receive a target amount of SOL using Jito
+#[allow(clippy::too_many_arguments)]
+pub async fn sell_for_sol_with_jito(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    payer: &dyn Signer,
+    jito_client: &JitoClient,
+    mint: &Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<String> {
+    let amount = get_sell_amount_for_sol(rpc, account_cache, &payer.pubkey(), mint, amount_sol).await?;
+    sell_with_jito(rpc, blockhash_cache, account_cache, payer, jito_client, mint, Some(amount), slippage_basis_points, tip_strategy, None).await
+}
+
+async fn get_sell_amount_for_sol(rpc: &RpcClient, account_cache: &AccountCache, payer: &Pubkey, mint: &Pubkey, amount_sol: u64) -> ClientResult<u64> {
+    if amount_sol == 0 {
+        return Err(ClientError::InvalidInput("Amount cannot be zero"));
+    }
+
+    let global_account = account_cache.get_global_account(rpc).await?;
+    let bonding_curve_account = account_cache.get_bonding_curve_account(rpc, mint).await?;
+    let amount = bonding_curve_account
+        .get_sell_token_amount(amount_sol, global_account.fee_basis_points)
+        .map_err(ClientError::BondingCurveError)?;
+
+    let balance_u64 = get_token_balance(rpc, payer, mint).await?;
+    if balance_u64 < amount {
+        return Err(ClientError::InsufficientBalance { required: amount, available: balance_u64 });
+    }
+
+    Ok(amount)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn build_sell_transaction(
     rpc: &RpcClient,
-    payer: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
     mint: &Pubkey,
     amount_token: Option<u64>,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Transaction, anyhow::Error> {
-    let instructions = build_sell_instructions(rpc, payer, mint, amount_token, slippage_basis_points, priority_fee).await?;
-    let recent_blockhash = rpc.get_latest_blockhash()?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer],
-        recent_blockhash,
-    );
+) -> ClientResult<Transaction> {
+    let instructions = build_sell_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), mint, amount_token, slippage_basis_points, priority_fee).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_transaction(&payer.pubkey(), &instructions, &[payer], recent_blockhash)
+}
 
-    Ok(transaction)
+/// Builds an unsigned sell transaction, for a separate signer to sign
+#[allow(clippy::too_many_arguments)]
+pub async fn build_sell_unsigned_transaction(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<Transaction> {
+    let instructions = build_sell_instructions(rpc, account_cache, fee_estimator, payer, mint, amount_token, slippage_basis_points, priority_fee).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    Ok(build_unsigned_transaction(payer, &instructions, recent_blockhash))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn build_sell_transaction_with_jito(
     rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
     jito_client: &JitoClient,
-    payer: &Keypair,
+    payer: &dyn Signer,
     mint: &Pubkey,
     amount_token: Option<u64>,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<Transaction, anyhow::Error> {
-    let instructions = build_sell_instructions_with_jito(rpc, jito_client, payer, mint, amount_token, slippage_basis_points, jito_fee).await?;
-    let recent_blockhash = rpc.get_latest_blockhash()?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer],
-        recent_blockhash,
-    );
-
-    Ok(transaction)
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<Transaction> {
+    let instructions = build_sell_instructions_with_jito(rpc, account_cache, jito_client, &payer.pubkey(), mint, amount_token, slippage_basis_points, tip_strategy).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_transaction(&payer.pubkey(), &instructions, &[payer], recent_blockhash)
 }
 
-pub async fn build_sell_instructions(
+/// Builds a v0 sell transaction that loads shared accounts from the lookup tables
+#[allow(clippy::too_many_arguments)]
+pub async fn build_sell_versioned_transaction(
     rpc: &RpcClient,
-    payer: &Keypair,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &dyn Signer,
     mint: &Pubkey,
     amount_token: Option<u64>,
     slippage_basis_points: Option<u64>,
     priority_fee: Option<PriorityFee>,
-) -> Result<Vec<Instruction>, anyhow::Error> {
-    let (balance_u64, ata) = get_token_balance(rpc, payer, mint).await?;
-    let amount = amount_token.unwrap_or(balance_u64);
-    
-    if amount == 0 {
-        return Err(anyhow!("Amount cannot be zero"));
-    }
-    
-    let global_account = get_global_account(rpc).await?;
-    let bonding_curve_account = get_bonding_curve_account(rpc, mint).await?;
-    let min_sol_output = bonding_curve_account
-        .get_sell_price(amount, global_account.fee_basis_points)
-        .map_err(|e| anyhow!(e))?;
-    let min_sol_output_with_slippage = calculate_with_slippage_sell(
-        min_sol_output,
-        slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
-    );
-
-    let mut instructions = vec![
-        ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
-        ComputeBudgetInstruction::set_compute_unit_price(0),
-    ];
-
-    instructions.push(instruction::sell(
-        payer,
-        mint,
-        &global_account.fee_recipient,
-        instruction::Sell {
-            _amount: amount,
-            _min_sol_output: min_sol_output_with_slippage,
-        },
-    ));
-
-    instructions.push(close_account(
-        &spl_token::ID,
-        &ata,
-        &payer.pubkey(),
-        &payer.pubkey(),
-        &[&payer.pubkey()],
-    )?);
-
-    let commitment_config = CommitmentConfig::confirmed();
-    let recent_blockhash = rpc.get_latest_blockhash_with_commitment(commitment_config)?
-        .0;
-
-    let simulate_tx = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer],
-        recent_blockhash,
-    );
-
-    let config = RpcSimulateTransactionConfig {
-        sig_verify: true,
-        commitment: Some(commitment_config),
-        ..RpcSimulateTransactionConfig::default()
-    };
-    
-    let result = rpc.simulate_transaction_with_config(&simulate_tx, config)?
-        .value;
-
-    if result.logs.as_ref().map_or(true, |logs| logs.is_empty()) {
-        return Err(anyhow!("Simulation failed: {:?}", result.err));
-    }
-
-    let result_cu = result.units_consumed.ok_or_else(|| anyhow!("No compute units consumed"))?;
-    let fees = rpc.get_recent_prioritization_fees(&[])?;
-    let average_fees = if fees.is_empty() {
-        DEFAULT_COMPUTE_UNIT_PRICE
-    } else {
-        fees.iter()
-            .map(|fee| fee.prioritization_fee)
-            .sum::<u64>() / fees.len() as u64
-    };
-
-    let unit_price = match priority_fee {
-        None => average_fees,
-        Some(pf) => pf.price.unwrap_or(DEFAULT_COMPUTE_UNIT_PRICE)
-    };
-
-    let unit_price = if unit_price == 0 { DEFAULT_COMPUTE_UNIT_PRICE } else { unit_price };
+    lookup_tables: &[AddressLookupTableAccount],
+) -> ClientResult<VersionedTransaction> {
+    let instructions = build_sell_instructions(rpc, account_cache, fee_estimator, &payer.pubkey(), mint, amount_token, slippage_basis_points, priority_fee).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_versioned_transaction(&payer.pubkey(), &instructions, &[payer], lookup_tables, recent_blockhash)
+}
 
-    instructions[0] = ComputeBudgetInstruction::set_compute_unit_limit(result_cu as u32);
-    instructions[1] = ComputeBudgetInstruction::set_compute_unit_price(unit_price);
+/// Builds a v0 Jito sell transaction that loads shared accounts from the lookup tables
+#[allow(clippy::too_many_arguments)]
+pub async fn build_sell_versioned_transaction_with_jito(
+    rpc: &RpcClient,
+    blockhash_cache: &BlockhashCache,
+    account_cache: &AccountCache,
+    jito_client: &JitoClient,
+    payer: &dyn Signer,
+    mint: &Pubkey,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+    tip_strategy: Option<TipStrategy>,
+    lookup_tables: &[AddressLookupTableAccount],
+) -> ClientResult<VersionedTransaction> {
+    let instructions = build_sell_instructions_with_jito(rpc, account_cache, jito_client, &payer.pubkey(), mint, amount_token, slippage_basis_points, tip_strategy).await?;
+    let recent_blockhash = blockhash_cache.get_blockhash().await?;
+    build_versioned_transaction(&payer.pubkey(), &instructions, &[payer], lookup_tables, recent_blockhash)
+}
 
-    Ok(instructions)
+#[allow(clippy::too_many_arguments)]
+pub async fn build_sell_instructions(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    fee_estimator: &FeeEstimator,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+    priority_fee: Option<PriorityFee>,
+) -> ClientResult<Vec<Instruction>> {
+    let instructions = build_sell_bundle_instructions(rpc, account_cache, payer, mint, amount_token, slippage_basis_points).await?;
+    add_compute_budget_instructions(rpc, fee_estimator, payer, mint, instructions, priority_fee).await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn build_sell_instructions_with_jito(
     rpc: &RpcClient,
+    account_cache: &AccountCache,
     jito_client: &JitoClient,
-    payer: &Keypair,
+    payer: &Pubkey,
     mint: &Pubkey,
     amount_token: Option<u64>,
     slippage_basis_points: Option<u64>,
-    jito_fee: Option<f64>,
-) -> Result<Vec<Instruction>, anyhow::Error> {
-    let (balance_u64, ata) = get_token_balance(rpc, payer, mint).await?;
-    let amount = amount_token.unwrap_or(balance_u64);
-    
-    if amount == 0 {
-        return Err(anyhow!("Amount cannot be zero"));
-    }
-    
-    let global_account = get_global_account(rpc).await?;
-    let bonding_curve_account = get_bonding_curve_account(rpc, mint).await?;
-    let min_sol_output = bonding_curve_account
-        .get_sell_price(amount, global_account.fee_basis_points)
-        .map_err(|e| anyhow!(e))?;
-    let min_sol_output_with_slippage = calculate_with_slippage_sell(
-        min_sol_output,
-        slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
-    );
+    tip_strategy: Option<TipStrategy>,
+) -> ClientResult<Vec<Instruction>> {
+    let mut instructions = build_sell_bundle_instructions(rpc, account_cache, payer, mint, amount_token, slippage_basis_points).await?;
 
-    let mut instructions = vec![];
-    instructions.push(instruction::sell(
-        payer,
-        mint,
-        &global_account.fee_recipient,
-        instruction::Sell {
-            _amount: amount,
-            _min_sol_output: min_sol_output_with_slippage,
-        },
-    ));
+    let tip_lamports = jito_client.resolve_tip(tip_strategy.unwrap_or_default()).await?;
+    instructions.push(build_jito_tip_instruction(jito_client, payer, tip_lamports).await?);
 
-    instructions.push(close_account(
-        &spl_token::ID,
-        &ata,
-        &payer.pubkey(),
-        &payer.pubkey(),
-        &[&payer.pubkey()],
-    )?);
+    Ok(instructions)
+}
 
-    let tip_account = jito_client.get_tip_account().await.map_err(|e| anyhow!(e))?;
-    let jito_fee = jito_fee.unwrap_or(JITO_TIP_AMOUNT);
-    instructions.push(
-        system_instruction::transfer(
-            &payer.pubkey(),
-            &tip_account,
-            sol_to_lamports(jito_fee),
-        ),
-    );
+async fn build_sell_bundle_instructions(
+    rpc: &RpcClient,
+    account_cache: &AccountCache,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+) -> ClientResult<Vec<Instruction>> {
+    let balance_u64 = get_token_balance(rpc, payer, mint).await?;
+    let global_account = account_cache.get_global_account(rpc).await?;
+    let bonding_curve_account = account_cache.get_bonding_curve_account(rpc, mint).await?;
+    let state = TradeState {
+        global_account: &global_account,
+        bonding_curve_account: Some(&bonding_curve_account),
+        ata_exists: true,
+    };
 
-    Ok(instructions)
+    offline::build_sell_instructions(payer, mint, &state, balance_u64, amount_token, slippage_basis_points)
 }
@@ -0,0 +1,407 @@
+//! Transaction submission over RPC
+//!
+//! A signed transaction is sent once with preflight checks, then sent again every rebroadcast
+//! interval until it is confirmed or the block height passes the last valid block height of its
+//! blockhash. Past that height the transaction can no longer land, so the outcome is final either
+//! way.
+//!
+//! Once the transaction is sent, RPC errors are logged and the request is made again on the next
+//! interval, so the signature of a transaction that may still land is never lost to an error. If
+//! the node keeps failing for `SUBMIT_TIMEOUT`, the submission ends with a timeout error carrying
+//! the signature.
+
+use std::time::Duration;
+
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::{RpcSendTransactionConfig, RpcTransactionConfig}};
+use solana_rpc_client::rpc_client::SerializableTransaction;
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig, signature::Signature, transaction::TransactionError};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::{
+    constants::trade::{OUTCOME_FETCH_ATTEMPTS, REBROADCAST_INTERVAL, SUBMIT_TIMEOUT},
+    error::{ClientError, ClientResult, TransactionFailure},
+};
+
+/// Final state of a submitted transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionOutcome {
+    /// The transaction landed and succeeded
+    Landed {
+        signature: Signature,
+        slot: Slot,
+        /// Fee paid in lamports, None if the node did not return the transaction
+        fee: Option<u64>,
+    },
+    /// The transaction landed and failed, it still paid its fee
+    Failed {
+        signature: Signature,
+        slot: Slot,
+        fee: Option<u64>,
+        failure: Box<TransactionFailure>,
+    },
+    /// The blockhash expired before the transaction landed
+    Expired {
+        signature: Signature,
+    },
+}
+
+impl TransactionOutcome {
+    pub fn signature(&self) -> &Signature {
+        match self {
+            Self::Landed { signature, .. } | Self::Failed { signature, .. } | Self::Expired { signature } => signature,
+        }
+    }
+
+    /// Returns true if the transaction landed and succeeded
+    pub fn is_landed(&self) -> bool {
+        matches!(self, Self::Landed { .. })
+    }
+
+    /// Returns the signature of a landed transaction, or the failure or expiry as an error
+    pub fn into_result(self) -> ClientResult<Signature> {
+        match self {
+            Self::Landed { signature, .. } => Ok(signature),
+            Self::Failed { failure, .. } => Err(ClientError::transaction_failed(*failure)),
+            Self::Expired { signature } => Err(ClientError::Timeout(
+                "Blockhash expired before the transaction landed".to_string(),
+                signature.to_string(),
+            )),
+        }
+    }
+
+    fn new(signature: Signature, slot: Slot, fee: Option<u64>, error: Option<TransactionError>, logs: Vec<String>) -> Self {
+        match error {
+            None => Self::Landed { signature, slot, fee },
+            Some(error) => Self::Failed {
+                signature,
+                slot,
+                fee,
+                failure: Box::new(TransactionFailure::new(error, logs)),
+            },
+        }
+    }
+}
+
+/// Sends `transaction` and rebroadcasts it every `rebroadcast_interval`, `REBROADCAST_INTERVAL`
+/// when None, until it is confirmed or the block height passes `last_valid_block_height`
+///
+/// A transaction that fails its preflight check is returned as an error without being
+/// rebroadcast. If the node does not return a confirmed transaction after
+/// `OUTCOME_FETCH_ATTEMPTS` tries, as with nodes without transaction history, the outcome is
+/// taken from the signature status, without the fee and logs. If neither the confirmation nor the
+/// expiry can be read within `SUBMIT_TIMEOUT`, a `ClientError::Timeout` with the signature is
+/// returned.
+pub async fn submit_transaction(
+    rpc: &RpcClient,
+    transaction: &impl SerializableTransaction,
+    last_valid_block_height: u64,
+    rebroadcast_interval: Option<Duration>,
+) -> ClientResult<TransactionOutcome> {
+    let rebroadcast_interval = rebroadcast_interval.unwrap_or(REBROADCAST_INTERVAL);
+    let commitment = CommitmentConfig::confirmed();
+    let rebroadcast_config = RpcSendTransactionConfig {
+        skip_preflight: true,
+        max_retries: Some(0),
+        ..RpcSendTransactionConfig::default()
+    };
+
+    let signature = rpc.send_transaction_with_config(transaction, RpcSendTransactionConfig {
+        max_retries: Some(0),
+        ..RpcSendTransactionConfig::default()
+    }).await?;
+
+    let deadline = Instant::now() + SUBMIT_TIMEOUT;
+    let mut outcome_attempts = 0;
+    loop {
+        tokio::time::sleep(rebroadcast_interval).await;
+
+        let status = rpc.get_signature_statuses(&[signature]).await
+            .inspect_err(|err| warn!("Failed to get the status of {signature}: {err}"))
+            .map(|statuses| statuses.value.into_iter().next().flatten());
+        match status {
+            Ok(Some(status)) if status.satisfies_commitment(commitment) => {
+                // The transaction can take a moment to show up in getTransaction
+                if let Some(outcome) = get_outcome(rpc, &signature, commitment).await {
+                    return Ok(outcome);
+                }
+                outcome_attempts += 1;
+                if outcome_attempts >= OUTCOME_FETCH_ATTEMPTS {
+                    return Ok(TransactionOutcome::new(signature, status.slot, None, status.err, Vec::new()));
+                }
+            }
+            // Processed on a fork that may still confirm, sending it again changes nothing
+            Ok(Some(_)) => {}
+            // Not seen by the node, or its status is unknown
+            Ok(None) | Err(_) => {
+                match rpc.get_block_height_with_commitment(commitment).await {
+                    Ok(block_height) if block_height > last_valid_block_height => {
+                        return Ok(TransactionOutcome::Expired { signature });
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!("Failed to get the block height for {signature}: {err}"),
+                }
+                // A failed rebroadcast is retried on the next interval
+                let _ = rpc.send_transaction_with_config(transaction, rebroadcast_config).await;
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return Err(ClientError::Timeout("Transaction outcome could not be read in time".to_string(), signature.to_string()));
+        }
+    }
+}
+
+/// Reads the slot, fee and error of a confirmed transaction, None if the node has not indexed it
+async fn get_outcome(rpc: &RpcClient, signature: &Signature, commitment: CommitmentConfig) -> Option<TransactionOutcome> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(commitment),
+        max_supported_transaction_version: Some(0),
+    };
+    let transaction = rpc.get_transaction_with_config(signature, config).await.ok()?;
+    let meta = transaction.transaction.meta?;
+    let logs = match meta.log_messages {
+        OptionSerializer::Some(logs) => logs,
+        _ => Vec::new(),
+    };
+
+    Some(TransactionOutcome::new(*signature, transaction.slot, Some(meta.fee), meta.err, logs))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use solana_rpc_client::{
+        rpc_client::RpcClientConfig,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    };
+    use solana_rpc_client_api::{
+        client_error::{Error, ErrorKind, Result},
+        request::RpcRequest,
+    };
+    use solana_sdk::{hash::Hash, instruction::InstructionError, signature::Keypair, signer::Signer, transaction::Transaction};
+    use solana_transaction_status::{
+        EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, EncodedTransactionWithStatusMeta,
+        TransactionConfirmationStatus, TransactionStatus, TransactionStatusMeta, UiTransactionStatusMeta,
+    };
+
+    use crate::{constants::accounts::PUMPFUN, error::PumpFunProgramError};
+
+    use super::*;
+
+    /// Scripted answers of a mock RPC node, None answers with an error
+    #[derive(Default)]
+    struct MockNode {
+        signature: Signature,
+        /// Answers of `getSignatureStatuses` in order, the last one repeats
+        statuses: VecDeque<Option<Option<TransactionStatus>>>,
+        /// Answers of `getBlockHeight` in order, the last one repeats
+        block_heights: VecDeque<Option<u64>>,
+        /// Fee of the transaction returned by `getTransaction`, None returns no transaction
+        fee: Option<u64>,
+        sends: usize,
+        transaction_requests: usize,
+    }
+
+    #[derive(Clone)]
+    struct MockSender(Arc<Mutex<MockNode>>);
+
+    fn next<T: Clone>(answers: &mut VecDeque<T>) -> T {
+        if answers.len() > 1 {
+            answers.pop_front().unwrap()
+        } else {
+            answers[0].clone()
+        }
+    }
+
+    #[async_trait]
+    impl RpcSender for MockSender {
+        async fn send(&self, request: RpcRequest, _params: Value) -> Result<Value> {
+            let mut node = self.0.lock().unwrap();
+            let error = || Error::from(ErrorKind::Custom("unavailable".to_string()));
+            match request {
+                RpcRequest::SendTransaction => {
+                    node.sends += 1;
+                    Ok(json!(node.signature.to_string()))
+                }
+                RpcRequest::GetSignatureStatuses => {
+                    let status = next(&mut node.statuses).ok_or_else(error)?;
+                    Ok(json!({ "context": { "slot": 100 }, "value": [status] }))
+                }
+                RpcRequest::GetBlockHeight => Ok(json!(next(&mut node.block_heights).ok_or_else(error)?)),
+                RpcRequest::GetTransaction => {
+                    node.transaction_requests += 1;
+                    let Some(fee) = node.fee else {
+                        return Ok(Value::Null);
+                    };
+                    let meta = TransactionStatusMeta { fee, log_messages: Some(vec![]), ..TransactionStatusMeta::default() };
+                    Ok(serde_json::to_value(EncodedConfirmedTransactionWithStatusMeta {
+                        slot: 100,
+                        transaction: EncodedTransactionWithStatusMeta {
+                            transaction: EncodedTransaction::LegacyBinary(String::new()),
+                            meta: Some(UiTransactionStatusMeta::from(meta)),
+                            version: None,
+                        },
+                        block_time: None,
+                    }).unwrap())
+                }
+                _ => panic!("unexpected request {request}"),
+            }
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "mock".to_string()
+        }
+    }
+
+    fn confirmed(slot: Slot, err: Option<TransactionError>) -> Option<TransactionStatus> {
+        Some(TransactionStatus {
+            slot,
+            confirmations: Some(1),
+            status: err.clone().map_or(Ok(()), Err),
+            err,
+            confirmation_status: Some(TransactionConfirmationStatus::Confirmed),
+        })
+    }
+
+    /// Returns a signed transaction and a client of a node that answers with `node`
+    fn mock_rpc(node: MockNode) -> (Transaction, RpcClient, Arc<Mutex<MockNode>>) {
+        let payer = Keypair::new();
+        let transaction = Transaction::new_signed_with_payer(&[], Some(&payer.pubkey()), &[&payer], Hash::new_unique());
+        let node = Arc::new(Mutex::new(MockNode { signature: transaction.signatures[0], ..node }));
+        let rpc = RpcClient::new_sender(MockSender(node.clone()), RpcClientConfig::with_commitment(CommitmentConfig::confirmed()));
+        (transaction, rpc, node)
+    }
+
+    #[test]
+    fn test_transaction_outcome() {
+        let signature = Signature::new_unique();
+
+        let landed = TransactionOutcome::new(signature, 100, Some(5_000), None, Vec::new());
+        assert_eq!(landed, TransactionOutcome::Landed { signature, slot: 100, fee: Some(5_000) });
+        assert!(landed.is_landed());
+        assert_eq!(landed.into_result().unwrap(), signature);
+
+        let logs = vec![format!("Program {} failed: custom program error: 0x1772", PUMPFUN)];
+        let error = TransactionError::InstructionError(2, InstructionError::Custom(6002));
+        let failed = TransactionOutcome::new(signature, 101, Some(5_000), Some(error), logs);
+        let TransactionOutcome::Failed { slot, fee, ref failure, .. } = failed else {
+            panic!("expected a failed outcome");
+        };
+        assert_eq!((slot, fee), (101, Some(5_000)));
+        assert_eq!(failure.instruction_index, Some(2));
+        assert_eq!(failure.program_error, Some(PumpFunProgramError::TooMuchSolRequired));
+        assert!(!failed.is_landed());
+        assert!(matches!(failed.into_result(), Err(ClientError::SlippageExceeded(_))));
+
+        let expired = TransactionOutcome::Expired { signature };
+        assert_eq!(expired.signature(), &signature);
+        assert!(matches!(expired.into_result(), Err(ClientError::Timeout(_, _))));
+    }
+
+    #[tokio::test]
+    async fn test_submit_transaction_send_error() {
+        let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+        let payer = Keypair::new();
+        let transaction = Transaction::new_signed_with_payer(&[], Some(&payer.pubkey()), &[&payer], Hash::new_unique());
+
+        assert!(submit_transaction(&rpc, &transaction, 0, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_submit_transaction_rebroadcasts_until_confirmed() {
+        tokio::time::pause();
+        let (transaction, rpc, node) = mock_rpc(MockNode {
+            // A failed status request is made again on the next interval
+            statuses: VecDeque::from([Some(None), None, Some(None), Some(confirmed(100, None))]),
+            block_heights: VecDeque::from([None, Some(120)]),
+            fee: Some(5_000),
+            ..MockNode::default()
+        });
+
+        let outcome = submit_transaction(&rpc, &transaction, 150, None).await.unwrap();
+        assert_eq!(outcome, TransactionOutcome::Landed { signature: transaction.signatures[0], slot: 100, fee: Some(5_000) });
+
+        // Sent once, then again for each interval without a status, failed requests included
+        let node = node.lock().unwrap();
+        assert_eq!(node.sends, 4);
+        assert_eq!(node.transaction_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_submit_transaction_expires() {
+        tokio::time::pause();
+        let (transaction, rpc, node) = mock_rpc(MockNode {
+            statuses: VecDeque::from([Some(None)]),
+            block_heights: VecDeque::from([Some(140), Some(150), Some(151)]),
+            ..MockNode::default()
+        });
+
+        let outcome = submit_transaction(&rpc, &transaction, 150, None).await.unwrap();
+        assert_eq!(outcome, TransactionOutcome::Expired { signature: transaction.signatures[0] });
+        assert_eq!(node.lock().unwrap().sends, 3);
+    }
+
+    #[tokio::test]
+    async fn test_submit_transaction_status_errors() {
+        tokio::time::pause();
+        // The expiry is still checked while every status request fails
+        let (transaction, rpc, _) = mock_rpc(MockNode {
+            statuses: VecDeque::from([None]),
+            block_heights: VecDeque::from([Some(140), Some(151)]),
+            ..MockNode::default()
+        });
+        let outcome = submit_transaction(&rpc, &transaction, 150, None).await.unwrap();
+        assert_eq!(outcome, TransactionOutcome::Expired { signature: transaction.signatures[0] });
+
+        // A node that answers nothing ends the submission at the deadline
+        let (transaction, rpc, node) = mock_rpc(MockNode {
+            statuses: VecDeque::from([None]),
+            block_heights: VecDeque::from([None]),
+            ..MockNode::default()
+        });
+        let start = Instant::now();
+        let result = submit_transaction(&rpc, &transaction, 150, None).await;
+        let Err(ClientError::Timeout(_, signature)) = result else {
+            panic!("expected a timeout");
+        };
+        assert_eq!(signature, transaction.signatures[0].to_string());
+        assert!(start.elapsed() >= SUBMIT_TIMEOUT);
+        assert!(start.elapsed() < SUBMIT_TIMEOUT + REBROADCAST_INTERVAL * 2);
+        assert!(node.lock().unwrap().sends > 1);
+    }
+
+    #[tokio::test]
+    async fn test_submit_transaction_outcome_from_status() {
+        tokio::time::pause();
+        let error = TransactionError::InstructionError(2, InstructionError::Custom(6002));
+        let (transaction, rpc, node) = mock_rpc(MockNode {
+            statuses: VecDeque::from([Some(confirmed(100, Some(error)))]),
+            block_heights: VecDeque::from([Some(200)]),
+            ..MockNode::default()
+        });
+
+        // The node never returns the transaction, so its status gives the outcome without a fee,
+        // even once the blockhash has expired
+        let outcome = submit_transaction(&rpc, &transaction, 150, None).await.unwrap();
+        let TransactionOutcome::Failed { slot, fee, ref failure, .. } = outcome else {
+            panic!("expected a failed outcome");
+        };
+        assert_eq!((slot, fee), (100, None));
+        assert_eq!(failure.program_error, Some(PumpFunProgramError::TooMuchSolRequired));
+        assert_eq!(node.lock().unwrap().transaction_requests, OUTCOME_FETCH_ATTEMPTS as usize);
+        assert_eq!(node.lock().unwrap().sends, 1);
+    }
+}